@@ -1,16 +1,33 @@
 // Copyright 2024-2025 Irreducible Inc.
 
+#[cfg(not(feature = "gix-backend"))]
 use git2::{Repository, StatusOptions};
+#[cfg(feature = "gix-backend")]
+use gix::bstr::ByteSlice;
 use std::env;
 
 fn main() {
-    // Only capture build-time metadata if the gen_filename feature is enabled
-    if env::var("CARGO_FEATURE_GEN_FILENAME").is_ok() {
+    // Only capture build-time metadata if something consumes it: `gen_filename` bakes git info
+    // into generated trace filenames, and `perfetto` attaches the full set to every trace's
+    // run-metadata event (see `emit_run_metadata`), so a recorded trace is self-describing.
+    if env::var("CARGO_FEATURE_GEN_FILENAME").is_ok() || env::var("CARGO_FEATURE_PERFETTO").is_ok() {
         // Capture target platform information (what we're compiling FOR)
         capture_platform_info();
 
         // Capture git repository information
         capture_git_info();
+
+        // Capture the toolchain and profile that's compiling us
+        capture_toolchain_info();
+
+        // Capture the set of enabled cargo features
+        capture_feature_flags();
+
+        // Capture the resolved dependency graph from Cargo.lock
+        capture_dependency_info();
+
+        // Capture whether we're building in CI, and under which provider
+        capture_ci_info();
     }
 }
 
@@ -34,6 +51,46 @@ fn capture_platform_info() {
     );
 }
 
+/// Formats a Unix timestamp plus a UTC offset (in minutes) as RFC3339, e.g.
+/// `2024-01-02T03:04:05+05:30`. Hand-rolled (using Howard Hinnant's `civil_from_days`
+/// algorithm) to avoid pulling a datetime crate into `build.rs` just for this.
+fn format_rfc3339(unix_seconds: i64, offset_minutes: i32) -> String {
+    let local_seconds = unix_seconds + i64::from(offset_minutes) * 60;
+    let days = local_seconds.div_euclid(86400);
+    let secs_of_day = local_seconds.rem_euclid(86400);
+
+    let (year, month, day) = civil_from_days(days);
+    let hour = secs_of_day / 3600;
+    let minute = (secs_of_day % 3600) / 60;
+    let second = secs_of_day % 60;
+
+    let sign = if offset_minutes < 0 { '-' } else { '+' };
+    let offset_minutes = offset_minutes.abs();
+    let offset_hours = offset_minutes / 60;
+    let offset_mins = offset_minutes % 60;
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}{sign}{offset_hours:02}:{offset_mins:02}"
+    )
+}
+
+/// Howard Hinnant's `civil_from_days`: converts a day count since the Unix epoch into a
+/// `(year, month, day)` proleptic-Gregorian civil date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = (z - era * 146097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let y = yoe as i64 + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = if month <= 2 { y + 1 } else { y };
+    (year, month, day)
+}
+
+#[cfg(not(feature = "gix-backend"))]
 fn capture_git_info() {
     // Check if we're being built as a dependency
     let is_primary = env::var("CARGO_PRIMARY_PACKAGE").is_ok();
@@ -105,10 +162,8 @@ fn capture_git_info() {
                 .map(|s| s.to_string())
                 .unwrap_or_default();
 
-            // Format commit time as RFC3339 (ISO-8601)
-            // For simplicity, we'll just leave it empty for now
-            // (it's optional and only used in Perfetto metadata)
-            let commit_time = String::new();
+            let time = commit.time();
+            let commit_time = format_rfc3339(time.seconds(), time.offset_minutes());
 
             (commit_short, commit_message, commit_author, commit_time)
         })
@@ -141,3 +196,196 @@ fn capture_git_info() {
     println!("cargo:rustc-env=BUILD_GIT_COMMIT_AUTHOR={commit_author}");
     println!("cargo:rustc-env=BUILD_GIT_COMMIT_TIME={commit_time}");
 }
+
+/// Like the `git2`-backed [`capture_git_info`] above, but built on the pure-Rust `gix` crate
+/// instead, so a build that can't (or doesn't want to) link libgit2's C code still gets the same
+/// `BUILD_GIT_*` env vars. Enabled via the `gix-backend` feature; `git2` remains the default.
+#[cfg(feature = "gix-backend")]
+fn capture_git_info() {
+    let is_primary = env::var("CARGO_PRIMARY_PACKAGE").is_ok();
+
+    let repo_path = if !is_primary {
+        if let Ok(pwd) = env::var("PWD") {
+            println!("cargo:warning=Best effort: Attempting to use git info from: {}", pwd);
+            pwd
+        } else {
+            println!("cargo:warning=PWD not available, using library's own git info");
+            ".".to_string()
+        }
+    } else {
+        ".".to_string()
+    };
+
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/index");
+
+    let repo = match gix::discover(&repo_path) {
+        Ok(repo) => repo,
+        Err(_) => {
+            println!("cargo:rustc-env=BUILD_GIT_BRANCH=unknown");
+            println!("cargo:rustc-env=BUILD_GIT_COMMIT_SHORT=unknown");
+            println!("cargo:rustc-env=BUILD_GIT_DIRTY=false");
+            println!("cargo:rustc-env=BUILD_GIT_COMMIT_MESSAGE=");
+            println!("cargo:rustc-env=BUILD_GIT_COMMIT_AUTHOR=");
+            println!("cargo:rustc-env=BUILD_GIT_COMMIT_TIME=");
+            return;
+        }
+    };
+
+    let branch = repo
+        .head_name()
+        .ok()
+        .flatten()
+        .map(|name| name.shorten().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    if !branch.starts_with("HEAD") && branch != "unknown" {
+        println!("cargo:rerun-if-changed=.git/refs/heads/{branch}");
+    }
+
+    let (commit_short, commit_message, commit_author, commit_time) = repo
+        .head_commit()
+        .ok()
+        .map(|commit| {
+            let commit_short = commit.id().to_string()[..7].to_string();
+            let commit_message = commit
+                .message()
+                .map(|m| String::from_utf8_lossy(m.title.trim()).to_string())
+                .unwrap_or_default();
+            let commit_author = commit
+                .author()
+                .map(|sig| sig.name.to_string())
+                .unwrap_or_default();
+            let commit_time = commit
+                .time()
+                .map(|time| format_rfc3339(time.seconds, time.offset / 60))
+                .unwrap_or_default();
+
+            (commit_short, commit_message, commit_author, commit_time)
+        })
+        .unwrap_or_else(|| {
+            (
+                "unknown".to_string(),
+                String::new(),
+                String::new(),
+                String::new(),
+            )
+        });
+
+    let is_clean = repo.is_dirty().map(|dirty| !dirty).unwrap_or(true);
+
+    println!("cargo:rustc-env=BUILD_GIT_BRANCH={branch}");
+    println!("cargo:rustc-env=BUILD_GIT_COMMIT_SHORT={commit_short}");
+    println!("cargo:rustc-env=BUILD_GIT_DIRTY={}", !is_clean);
+    println!("cargo:rustc-env=BUILD_GIT_COMMIT_MESSAGE={commit_message}");
+    println!("cargo:rustc-env=BUILD_GIT_COMMIT_AUTHOR={commit_author}");
+    println!("cargo:rustc-env=BUILD_GIT_COMMIT_TIME={commit_time}");
+}
+
+/// Captures the rustc version and cargo's own view of the build (profile, optimization level,
+/// debug-info setting, host triple), so a trace can be tied back to exactly how its binary was
+/// compiled.
+fn capture_toolchain_info() {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = std::process::Command::new(&rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|version| version.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=BUILD_RUSTC_VERSION={rustc_version}");
+
+    println!(
+        "cargo:rustc-env=BUILD_PROFILE={}",
+        env::var("PROFILE").unwrap_or_else(|_| "unknown".to_string())
+    );
+    println!(
+        "cargo:rustc-env=BUILD_OPT_LEVEL={}",
+        env::var("OPT_LEVEL").unwrap_or_else(|_| "unknown".to_string())
+    );
+    println!(
+        "cargo:rustc-env=BUILD_DEBUG={}",
+        env::var("DEBUG").unwrap_or_else(|_| "unknown".to_string())
+    );
+    println!(
+        "cargo:rustc-env=BUILD_HOST_TRIPLE={}",
+        env::var("HOST").unwrap_or_else(|_| "unknown".to_string())
+    );
+}
+
+/// Captures the exact set of enabled cargo features by walking the `CARGO_FEATURE_*` env vars
+/// cargo sets for us, rather than trying to keep a hand-maintained list in sync with `Cargo.toml`.
+fn capture_feature_flags() {
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| {
+            key.strip_prefix("CARGO_FEATURE_")
+                .map(|name| name.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+
+    println!("cargo:rustc-env=BUILD_FEATURES={}", features.join(","));
+}
+
+/// Captures the resolved dependency graph from `Cargo.lock`, so a trace can be matched back to
+/// the exact dependency versions that produced it.
+fn capture_dependency_info() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+    let lock_path = std::path::Path::new(&manifest_dir).join("Cargo.lock");
+    println!("cargo:rerun-if-changed={}", lock_path.display());
+
+    let dependencies = std::fs::read_to_string(&lock_path)
+        .map(|contents| parse_lockfile_packages(&contents))
+        .unwrap_or_default();
+
+    println!("cargo:rustc-env=BUILD_DEPENDENCIES={}", dependencies.join(","));
+}
+
+/// Pulls `name@version` pairs out of `Cargo.lock`'s `[[package]]` tables with a small hand-rolled
+/// parser, rather than pulling in a TOML crate just for build-time metadata. This lists every
+/// resolved package (the full dependency graph), not just this crate's direct dependencies, since
+/// `Cargo.lock` doesn't record that distinction on its own.
+fn parse_lockfile_packages(contents: &str) -> Vec<String> {
+    let mut packages = Vec::new();
+    let mut name: Option<String> = None;
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line == "[[package]]" {
+            name = None;
+        } else if let Some(value) = line.strip_prefix("name = ") {
+            name = Some(value.trim_matches('"').to_string());
+        } else if let Some(value) = line.strip_prefix("version = ") {
+            if let Some(name) = name.take() {
+                packages.push(format!("{name}@{}", value.trim_matches('"')));
+            }
+        }
+    }
+
+    packages
+}
+
+/// Detects whether we're building in CI, and under which provider, by checking the env vars the
+/// major CI systems set on their own runners.
+fn capture_ci_info() {
+    const CI_PROVIDERS: &[(&str, &str)] = &[
+        ("GITHUB_ACTIONS", "github_actions"),
+        ("GITLAB_CI", "gitlab_ci"),
+        ("CIRCLECI", "circleci"),
+        ("TRAVIS", "travis"),
+        ("JENKINS_URL", "jenkins"),
+        ("BUILDKITE", "buildkite"),
+        ("APPVEYOR", "appveyor"),
+    ];
+
+    let provider = CI_PROVIDERS
+        .iter()
+        .find(|(var, _)| env::var(var).is_ok())
+        .map(|(_, name)| *name);
+    let is_ci = provider.is_some() || env::var("CI").is_ok();
+
+    println!("cargo:rustc-env=BUILD_CI={is_ci}");
+    println!("cargo:rustc-env=BUILD_CI_PROVIDER={}", provider.unwrap_or("none"));
+}
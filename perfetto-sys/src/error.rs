@@ -8,4 +8,8 @@ pub enum Error {
     ProcessError(String, std::io::Error),
     #[error("external process {0} failed with code {1}")]
     ProcessReturnedError(String, i32),
+    #[error("trace file integrity check failed: {0}")]
+    IntegrityError(String),
+    #[error("trace file integrity unverified: {0}")]
+    Unverified(String),
 }
\ No newline at end of file
@@ -1,16 +1,95 @@
-use std::{ffi::{c_char, CString}, ptr::null, thread::{self, ThreadId}};
+use std::{ffi::{c_char, CStr, CString}, ptr::null, thread::{self, ThreadId}};
 use std::sync::{Mutex, OnceLock};
 use std::collections::HashMap;
- 
-// Get stable pointer for `key`
+
+use crate::track::TrackParent;
+
+/// A `&'static str` that's been (or will be) interned into a process-lifetime `CString`, so
+/// passing it to Perfetto's C API needs no allocation on the hot path. Interning is keyed by the
+/// `&str`'s pointer identity rather than its contents: the common case is the same `&'static str`
+/// literal reused at many call sites, which shares one entry without re-hashing the string on
+/// every lookup. Two different literals that simply happen to read the same text are stored
+/// separately — harmless, since each still yields a valid, process-lifetime pointer. Entries are
+/// never freed, so pointers handed out stay valid for the life of the process.
+#[derive(Debug, Clone, Copy)]
+pub struct StaticString(&'static str);
+
+impl StaticString {
+    pub fn new(value: &'static str) -> Self {
+        Self(value)
+    }
+
+    fn as_ptr(self) -> *const c_char {
+        static POOL: OnceLock<Mutex<HashMap<usize, CString>>> = OnceLock::new();
+        let pool = POOL.get_or_init(|| Mutex::new(HashMap::new()));
+        let mut pool = pool.lock().unwrap();
+        pool.entry(self.0.as_ptr() as usize)
+            .or_insert_with(|| CString::new(self.0).expect("invalid static string"))
+            .as_ptr()
+    }
+}
+
+impl From<&'static str> for StaticString {
+    fn from(value: &'static str) -> Self {
+        Self::new(value)
+    }
+}
+
+// Get stable pointer for `key`, interning it the first time it's seen.
 fn get_key_ptr(key: &'static str) -> *const c_char {
-    static KEY_POOL: OnceLock<Mutex<HashMap<&'static str, CString>>> = OnceLock::new();
-    let map = KEY_POOL.get_or_init(|| Mutex::new(HashMap::new()));
-    let mut guard = map.lock().unwrap();
-    guard
-        .entry(key)
-        .or_insert_with(|| CString::new(key).expect("invalid key string"))
-        .as_ptr()
+    StaticString::new(key).as_ptr()
+}
+
+/// Interns a category string into a process-lifetime pool keyed by content, mirroring
+/// [`StaticString`]'s pointer-identity pool but hashing the bytes instead: category values
+/// arrive as a plain `&str` (see [`EventData::set_category_interned`]) with no guaranteed
+/// `'static` lifetime, so pointer identity isn't available here. The common case — the same
+/// category text reused across many spans/events — then costs one hash lookup instead of a
+/// fresh `CString` allocation.
+///
+/// This only avoids the allocation on our side of the FFI boundary; `create_event`/
+/// `emit_counter_event` still receive the full category string every call, and whatever
+/// `interned_data`/iid encoding the linked Perfetto SDK does internally for `track_event` is out
+/// of this crate's control. So this pool shrinks our own CPU/allocation cost, not the bytes
+/// written to the trace.
+fn intern_category(category: &str) -> *const c_char {
+    static POOL: OnceLock<Mutex<HashMap<String, CString>>> = OnceLock::new();
+    let pool = POOL.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut pool = pool.lock().unwrap();
+    if !pool.contains_key(category) {
+        pool.insert(
+            category.to_string(),
+            CString::new(category).expect("category is not a valid string"),
+        );
+    }
+    pool.get(category).unwrap().as_ptr()
+}
+
+/// A category's backing storage: either a freshly allocated `CString` (the default path) or a
+/// pointer into [`intern_category`]'s process-lifetime pool (see
+/// [`EventData::set_category_interned`]).
+#[derive(Debug)]
+enum Category {
+    Owned(CString),
+    Interned(*const c_char),
+}
+
+impl Category {
+    fn as_ptr(&self) -> *const c_char {
+        match self {
+            Category::Owned(category) => category.as_ptr(),
+            Category::Interned(ptr) => *ptr,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Category::Owned(category) => category.to_str().ok(),
+            // Safety: `ptr` came from `intern_category`, which hands out pointers into CStrings
+            // it owns for the lifetime of the process.
+            Category::Interned(ptr) => unsafe { CStr::from_ptr(*ptr) }.to_str().ok(),
+        }
+    }
 }
 
 
@@ -28,6 +107,7 @@ enum ArgType {
 enum EventType {
     Span,
     Instant,
+    Counter,
 }
 
 #[repr(C)]
@@ -56,16 +136,40 @@ struct PerfettoArg {
 extern "C" {
     fn create_event(event_type: EventType, category: *const c_char, name: *const c_char, track_id: *const u64, args: *const PerfettoArg, arg_count: usize);
     fn destroy_event(category: *const c_char, track_id: *const u64);
+    fn emit_counter_event(event_type: EventType, track_id: u64, value: f64);
+}
+
+/// The event's name: either a freshly allocated `CString` (the dynamic, default path) or an
+/// already-interned [`StaticString`]'s pointer, reused with zero allocation (see
+/// [`EventData::new_static`]).
+enum EventName {
+    Owned(CString),
+    Static(*const c_char),
+}
+
+impl EventName {
+    fn as_ptr(&self) -> *const c_char {
+        match self {
+            EventName::Owned(name) => name.as_ptr(),
+            EventName::Static(ptr) => *ptr,
+        }
+    }
 }
 
 /// Represents a tracing event data.
 pub struct EventData {
     /// Name of the event.
-    name: CString,
+    name: EventName,
     /// Category of the event. If None the default will be used
-    category: Option<CString>,
+    category: Option<Category>,
     /// Track id of the event. If None the current thread track will be used.
     track_id: Option<u64>,
+    /// Display name for `track_id`'s track, if one was given via `set_track_name`. Only
+    /// meaningful alongside a custom `track_id`; ignored on the default thread track.
+    track_name: Option<CString>,
+    /// Where `track_id`'s `TrackDescriptor` should be anchored, if overridden via
+    /// `scope_to_process`/`scope_to_thread`. Defaults to `TrackParent::Process`.
+    track_parent: Option<TrackParent>,
     /// Information about custom fields and flow id
     args: Vec<PerfettoArg>,
     /// Storage for the strings in the args
@@ -77,20 +181,94 @@ impl EventData {
         Self {
             category: None,
             track_id: None,
-            name: CString::new(name).unwrap(),
+            track_name: None,
+            track_parent: None,
+            name: EventName::Owned(CString::new(name).unwrap()),
+            strings_storage: Vec::new(),
+            args: Vec::new(),
+        }
+    }
+
+    /// Like [`new`](Self::new), but takes an already-interned [`StaticString`] so constructing
+    /// the event allocates nothing on the hot path: the name is interned once, the first time
+    /// this `&'static str` is seen, and its pointer is reused on every subsequent call.
+    pub fn new_static(name: StaticString) -> Self {
+        Self {
+            category: None,
+            track_id: None,
+            track_name: None,
+            track_parent: None,
+            name: EventName::Static(name.as_ptr()),
             strings_storage: Vec::new(),
             args: Vec::new(),
         }
     }
 
     pub fn set_category(&mut self, category: &str) {
-        self.category = Some(CString::new(category).expect("category is not a valid string"));
+        self.category = Some(Category::Owned(
+            CString::new(category).expect("category is not a valid string"),
+        ));
+    }
+
+    /// Like [`set_category`](Self::set_category), but interns `category` by content into a
+    /// process-lifetime pool (see [`intern_category`]) instead of allocating a fresh `CString`
+    /// every call. Worth it once the same category text repeats across many spans/events, at the
+    /// cost of the pool's bookkeeping; opt in via `PerfettoSettings::intern_categories` /
+    /// `PERFETTO_INTERN_CATEGORIES`.
+    pub fn set_category_interned(&mut self, category: &str) {
+        self.category = Some(Category::Interned(intern_category(category)));
     }
 
     pub fn set_track_id(&mut self, track_id: u64) {
         self.track_id = Some(track_id);
     }
 
+    pub fn set_track_name(&mut self, name: &str) {
+        self.track_name = Some(CString::new(name).expect("track name is not a valid string"));
+    }
+
+    pub fn track_id(&self) -> Option<u64> {
+        self.track_id
+    }
+
+    pub fn track_name(&self) -> Option<&str> {
+        self.track_name.as_deref().and_then(|name| name.to_str().ok())
+    }
+
+    /// The category set via `set_category`/`set_category_interned`, or `None` if the default
+    /// category should be used.
+    pub fn category(&self) -> Option<&str> {
+        self.category.as_ref().and_then(Category::as_str)
+    }
+
+    /// Associates this event with a named async track: a process-wide `uuid` the caller picks
+    /// (e.g. a request id or GPU queue index), shown in the UI under `name`. Unlike the default
+    /// current-thread track, spans on an async track can nest and overlap arbitrarily on one
+    /// lane, since `create_event`/`destroy_event` key off the uuid rather than the calling
+    /// thread. Defaults to scoping the track under the current process; see
+    /// [`scope_to_process`](Self::scope_to_process)/[`scope_to_thread`](Self::scope_to_thread) to
+    /// override.
+    pub fn set_async_track(&mut self, uuid: u64, name: &str) {
+        self.track_id = Some(uuid);
+        self.track_name = Some(CString::new(name).expect("track name is not a valid string"));
+    }
+
+    /// Scopes this event's async track (see [`set_async_track`](Self::set_async_track)) under the
+    /// current process. This is the default.
+    pub fn scope_to_process(&mut self) {
+        self.track_parent = Some(TrackParent::Process);
+    }
+
+    /// Scopes this event's async track (see [`set_async_track`](Self::set_async_track)) under the
+    /// given OS thread rather than the process as a whole.
+    pub fn scope_to_thread(&mut self, thread_id: u64) {
+        self.track_parent = Some(TrackParent::Thread(thread_id));
+    }
+
+    pub fn track_parent(&self) -> TrackParent {
+        self.track_parent.unwrap_or(TrackParent::Process)
+    }
+
     pub fn set_flow_id(&mut self, flow_id: u64) {
         self.args.push(PerfettoArg {
             data: ArgValue { u64: flow_id },
@@ -139,10 +317,24 @@ impl EventData {
         });
         self.strings_storage.push(value);
     }
+
+    /// Like [`add_string_arg`](Self::add_string_arg), but for an already-interned
+    /// [`StaticString`] value, so it allocates nothing: no per-call `CString`, and nothing to
+    /// keep alive in `strings_storage` since the interned pointer is valid for the process's
+    /// lifetime.
+    pub fn add_static_string_arg(&mut self, key: &'static str, value: StaticString) {
+        let key_ptr = get_key_ptr(key);
+        self.args.push(PerfettoArg {
+            data: ArgValue { string_key_value: KeyValue { key: key_ptr, value: value.as_ptr() } },
+            arg_type: ArgType::StringKeyValue,
+        });
+    }
 }
 
 /// Safety: raw pointers in EventData.args remain valid because field key strings are stored globally (static lifetime),
-/// and any value strings are stored in this EventData's strings_storage.
+/// and any value strings are stored in this EventData's strings_storage. A `name: EventName::Static` pointer is
+/// likewise valid for the process's lifetime, since it's only ever handed out by `StaticString`'s process-lifetime
+/// interner.
 unsafe impl Send for EventData {}
 unsafe impl Sync for EventData {}
 
@@ -156,14 +348,14 @@ enum Track {
 #[derive(Debug)]
 pub struct TraceEvent {
     track: Track,
-    category: Option<CString>,
+    category: Option<Category>,
 }
 
 impl TraceEvent {
     pub fn new(event_data: EventData) -> Self {
         unsafe { create_event(
             EventType::Span,
-            event_data.category.as_ref().map(|s| s.as_ptr()).unwrap_or(null()), 
+            event_data.category.as_ref().map(|c| c.as_ptr()).unwrap_or(null()),
             event_data.name.as_ptr(),
             event_data.track_id.as_ref().map(|id| id as *const u64).unwrap_or(null()),
             event_data.args.as_ptr(), 
@@ -192,10 +384,20 @@ impl Drop for TraceEvent {
             },
         };
 
-        unsafe { destroy_event(self.category.as_ref().map(|s| s.as_ptr()).unwrap_or(null()), track_id) };
+        unsafe { destroy_event(self.category.as_ref().map(|c| c.as_ptr()).unwrap_or(null()), track_id) };
     }
 }
 
+/// Emits one sample on a dedicated counter track, rendered as a continuous line in the Perfetto
+/// UI instead of a static per-span arg. `track_id` is a process-wide unique id for the counter's
+/// track (a separate namespace from the async track uuids used by
+/// [`EventData::set_async_track`]); a `TrackDescriptor` naming it after `name` is emitted once,
+/// the first time `track_id` is seen.
+pub fn create_counter_event(name: &str, track_id: u64, value: f64) {
+    crate::track::set_counter_track_name(track_id, name);
+    unsafe { emit_counter_event(EventType::Counter, track_id, value) };
+}
+
 /// Emit the given `EventData` as a Perfetto instant event with all metadata.
 pub fn create_instant_event(event_data: EventData) {
     unsafe {
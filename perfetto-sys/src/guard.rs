@@ -1,8 +1,8 @@
 use std::{ffi::{c_void, CString}, io::Write, path::{Path, PathBuf}, process::{Child, Command}, thread, time::Duration};
-use crate::Error;
+use crate::{Error, TraceConfig};
 
 extern "C" {
-    fn init_perfetto(backend: u32, output_path: *const i8, buffer_size: usize) -> *mut c_void;
+    fn init_perfetto(backend: u32, output_path: *const i8, config: *const u8, config_len: usize) -> *mut c_void;
     fn deinit_perfetto(guard: *mut c_void);
 }
 
@@ -16,11 +16,13 @@ enum Backend {
 }
 
 /// Backend configuration for perfetto.
+#[derive(Debug, Clone)]
 pub enum BackendConfig {
     /// Use API to create a trace of the local process.
-    InProcess { 
-        /// Size of the buffer in kilobytes.
-        buffer_size_kb: usize 
+    InProcess {
+        /// Buffers, fill policies, data source routing and flush/clear periods, built with a
+        /// [`TraceConfigBuilder`](crate::TraceConfigBuilder).
+        trace_config: TraceConfig,
     },
     /// Use system wide tracing fused with the local process data.
     /// The `PerfettoGuard` will take care of starting and stopping the perfetto processes.
@@ -29,8 +31,10 @@ pub enum BackendConfig {
         /// If `None`, the system path will be used.
         perfetto_bin_path: Option<String>,
         /// Path to the perfetto config file.
-        /// If none the default one `config/system_profiling.cfg` will be used.
-        perfetto_cfg_path: Option<String>
+        /// If `None`, `trace_config` is rendered to a temp `.cfg` file instead.
+        perfetto_cfg_path: Option<String>,
+        /// Used to render the temp `.cfg` file when `perfetto_cfg_path` is `None`.
+        trace_config: TraceConfig,
     },
 }
 
@@ -42,18 +46,35 @@ impl BackendConfig {
         }
     }
 
-    fn buffer_size_kb(&self) -> usize {
+    fn trace_config(&self) -> &TraceConfig {
         match self {
-            BackendConfig::InProcess { buffer_size_kb } => *buffer_size_kb,
-            BackendConfig::System { .. } => 0,
+            BackendConfig::InProcess { trace_config } => trace_config,
+            BackendConfig::System { trace_config, .. } => trace_config,
         }
     }
 }
 
+/// How (if at all) [`PerfettoGuard`] should stamp the trace file with an integrity header on a
+/// clean drop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntegrityMode {
+    /// Don't write an integrity header.
+    #[default]
+    None,
+    /// Prepend the header directly onto the trace file (see [`crate::write_integrity_header`]).
+    InlineHeader,
+    /// Write the header to a `.integrity` sidecar file, leaving the trace file untouched (see
+    /// [`crate::write_integrity_sidecar`]).
+    Sidecar,
+}
+
 /// Create only one of these per tracing session. It should live for the duration of the program.
 pub struct PerfettoGuard {
     ptr: *mut c_void,
     processes: Option<PerfettoProcessesGuard>,
+    output_path: PathBuf,
+    integrity: IntegrityMode,
+    flush_period: Duration,
 }
 
 // Safety: the pointers here are heap allocated and not shared. Should be ok to send them to other threads
@@ -61,36 +82,84 @@ unsafe impl Send for PerfettoGuard {}
 unsafe impl Sync for PerfettoGuard {}
 
 impl PerfettoGuard {
-    /// Initializes tracing. 
+    /// Initializes tracing.
     pub fn new(backend: BackendConfig, output_path: &str) -> Result<Self, Error> {
+        Self::new_impl(backend, output_path, IntegrityMode::None)
+    }
+
+    /// Like [`new`](Self::new), but opts in to writing a companion integrity header (magic + byte
+    /// count + SHA-256 digest) over the trace file once it drops cleanly. Lets an unattended run
+    /// be checked for truncation/corruption later via [`crate::verify_trace_file`]. The header is
+    /// skipped on a panicking unwind, since the trace file itself may not be complete in that case.
+    pub fn new_with_integrity_header(backend: BackendConfig, output_path: &str) -> Result<Self, Error> {
+        Self::new_impl(backend, output_path, IntegrityMode::InlineHeader)
+    }
+
+    /// Like [`new_with_integrity_header`](Self::new_with_integrity_header), but writes the header
+    /// to a `.integrity` sidecar file next to the trace instead of prepending it, leaving the
+    /// trace file byte-for-byte untouched for tools that don't understand the header. Verify with
+    /// [`crate::verify_trace_sidecar`].
+    pub fn new_with_integrity_sidecar(backend: BackendConfig, output_path: &str) -> Result<Self, Error> {
+        Self::new_impl(backend, output_path, IntegrityMode::Sidecar)
+    }
+
+    fn new_impl(backend: BackendConfig, output_path: &str, integrity: IntegrityMode) -> Result<Self, Error> {
+        let flush_period = backend.trace_config().flush_period();
+
         let processes = match &backend {
-            BackendConfig::System { perfetto_bin_path, perfetto_cfg_path } => {
-                Some(PerfettoProcessesGuard::new(perfetto_bin_path.as_ref().map(|s| s.as_str()), output_path, perfetto_cfg_path.as_ref().map(|s| s.as_str()))?)
+            BackendConfig::System { perfetto_bin_path, perfetto_cfg_path, trace_config } => {
+                Some(PerfettoProcessesGuard::new(perfetto_bin_path.as_ref().map(|s| s.as_str()), output_path, perfetto_cfg_path.as_ref().map(|s| s.as_str()), trace_config)?)
             },
             BackendConfig::InProcess { .. } => {
                 None
             },
         };
-        
-        let output_path = CString::new(output_path).expect("output_path is not a valid string");
-        let buffer_size_kb = backend.buffer_size_kb();
+
+        let output_path_cstring = CString::new(output_path).expect("output_path is not a valid string");
+        let config_bytes = backend.trace_config().as_bytes().to_vec();
         let backend = backend.backend();
-        let ptr = unsafe { init_perfetto(backend as u32, output_path.as_ptr(), buffer_size_kb) };
-        
-        
-        Ok(Self { ptr, processes })
+        let ptr = unsafe { init_perfetto(backend as u32, output_path_cstring.as_ptr(), config_bytes.as_ptr(), config_bytes.len()) };
+
+
+        Ok(Self {
+            ptr,
+            processes,
+            output_path: PathBuf::from(output_path),
+            integrity,
+            flush_period,
+        })
     }
 }
 
 impl Drop for PerfettoGuard {
     fn drop(&mut self) {
-        // in wrapper.cc there's a 2 second flush interval. want to ensure all logs are flushed before stopping perfetto.
-        std::thread::sleep(Duration::from_millis(2500));
+        // Perfetto flushes on the configured interval; wait a bit past it so the last buffered
+        // events make it into the trace file before we stop the session.
+        std::thread::sleep(self.flush_period + Duration::from_millis(500));
         unsafe { deinit_perfetto(self.ptr) }
 
         self.processes.take().map(|mut processes| {
             _ = processes.stop_and_wait().expect("failed to stop perfetto processes");
         });
+
+        // Only stamp the header on a clean drop: during a panicking unwind (e.g. the `panic`
+        // feature turning an err_msg! into an abort) the trace file may not be fully flushed yet.
+        if std::thread::panicking() {
+            return;
+        }
+        match self.integrity {
+            IntegrityMode::None => {}
+            IntegrityMode::InlineHeader => {
+                if let Err(e) = crate::write_integrity_header(&self.output_path) {
+                    eprintln!("failed to write trace integrity header for {:?}: {e}", self.output_path);
+                }
+            }
+            IntegrityMode::Sidecar => {
+                if let Err(e) = crate::write_integrity_sidecar(&self.output_path) {
+                    eprintln!("failed to write trace integrity sidecar for {:?}: {e}", self.output_path);
+                }
+            }
+        }
     }
 }
 
@@ -102,10 +171,10 @@ struct PerfettoProcessesGuard {
 }
 
 impl PerfettoProcessesGuard {
-    fn new(bin_folder: Option<&str>, output_path: &str, config: Option<&str>) -> Result<Self, Error> {
+    fn new(bin_folder: Option<&str>, output_path: &str, config: Option<&str>, trace_config: &TraceConfig) -> Result<Self, Error> {
         let traced_probes = ProcessGuard::new("traced_probes".to_string(), Command::new(join_with_folder(bin_folder.clone(), "traced_probes")))?;
         let traced = ProcessGuard::new("traced".to_string(), Command::new(join_with_folder(bin_folder.clone(), "traced")))?;
-        
+
         let mut perfetto = Command::new(join_with_folder(bin_folder.clone(), "perfetto"));
         perfetto
             .arg("--txt")
@@ -119,7 +188,7 @@ impl PerfettoProcessesGuard {
             }
             None => {
                 let mut tmp_cfg = tempfile::NamedTempFile::new()?;
-                tmp_cfg.write_all(include_str!("../config/system_profiling.cfg").as_bytes())?;
+                tmp_cfg.write_all(trace_config.as_text_proto().as_bytes())?;
                 tmp_cfg.flush()?;
                 perfetto.arg(tmp_cfg.path().to_str().expect("invalid path"));
 
@@ -0,0 +1,192 @@
+// Copyright 2024-2025 Irreducible Inc.
+
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::Error;
+
+/// 16 bytes at the start of an integrity-wrapped trace file, used to tell it apart from a bare
+/// perfetto protobuf stream.
+const MAGIC: [u8; 16] = *b"TRACEPROFILE_V1\0";
+const LENGTH_SIZE: usize = 8;
+const DIGEST_SIZE: usize = 32;
+const HEADER_SIZE: usize = MAGIC.len() + LENGTH_SIZE + DIGEST_SIZE;
+
+/// Prepends a fixed-layout integrity header to the trace file at `path`: the 16-byte magic, the
+/// payload length as a little-endian `u64`, and the payload's SHA-256 digest. Rewrites the file
+/// in place (via a temporary file, renamed over the original), so shipping the trace between
+/// machines can later be checked for truncation/corruption with [`verify_trace_file`].
+pub fn write_integrity_header(path: &Path) -> Result<(), Error> {
+    let payload = fs::read(path)?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(&payload);
+    let digest = hasher.finalize();
+
+    let tmp_path = path.with_extension("integrity-tmp");
+    let mut tmp_file = File::create(&tmp_path)?;
+    tmp_file.write_all(&MAGIC)?;
+    tmp_file.write_all(&(payload.len() as u64).to_le_bytes())?;
+    tmp_file.write_all(&digest)?;
+    tmp_file.write_all(&payload)?;
+    tmp_file.flush()?;
+    drop(tmp_file);
+
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Verifies that the trace file at `path` carries a valid integrity header written by
+/// [`write_integrity_header`]: the magic matches, the recorded payload length matches the
+/// trailing bytes, and the recorded SHA-256 digest matches the payload.
+///
+/// A file that simply has no header (e.g. one produced without `integrity_header` enabled) is
+/// reported via the distinct [`Error::Unverified`] variant rather than [`Error::IntegrityError`],
+/// so callers can tell "nothing to check" apart from "this trace is actually corrupt".
+pub fn verify_trace_file(path: &Path) -> Result<(), Error> {
+    let data = fs::read(path)?;
+
+    if data.len() < HEADER_SIZE {
+        return Err(Error::Unverified(format!(
+            "{path:?} is too short to contain an integrity header"
+        )));
+    }
+
+    let (magic, rest) = data.split_at(MAGIC.len());
+    if magic != MAGIC {
+        return Err(Error::Unverified(format!(
+            "{path:?} does not start with the expected integrity header magic"
+        )));
+    }
+
+    let (length_bytes, rest) = rest.split_at(LENGTH_SIZE);
+    let expected_len = u64::from_le_bytes(length_bytes.try_into().unwrap()) as usize;
+
+    let (digest_bytes, payload) = rest.split_at(DIGEST_SIZE);
+
+    if payload.len() != expected_len {
+        return Err(Error::IntegrityError(format!(
+            "{path:?} has payload length {} but the header records {expected_len}",
+            payload.len()
+        )));
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(payload);
+    let actual_digest = hasher.finalize();
+
+    if actual_digest.as_slice() != digest_bytes {
+        return Err(Error::IntegrityError(format!(
+            "{path:?} failed its SHA-256 checksum"
+        )));
+    }
+
+    Ok(())
+}
+
+/// The sidecar filename for `path`'s integrity header: the same name with a `.integrity` suffix
+/// appended (not replacing `path`'s own extension), e.g. `trace.perfetto-trace.integrity`.
+fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".integrity");
+    PathBuf::from(name)
+}
+
+/// Like [`write_integrity_header`], but writes the magic, payload length and SHA-256 digest to a
+/// `.integrity` sidecar file next to `path` instead of prepending them to the trace itself,
+/// leaving the original `.perfetto-trace` byte-for-byte untouched for tools that don't understand
+/// the header.
+pub fn write_integrity_sidecar(path: &Path) -> Result<(), Error> {
+    let payload = fs::read(path)?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(&payload);
+    let digest = hasher.finalize();
+
+    let sidecar_path = sidecar_path(path);
+    let tmp_path = sidecar_path.with_extension("integrity-tmp");
+    let mut tmp_file = File::create(&tmp_path)?;
+    tmp_file.write_all(&MAGIC)?;
+    tmp_file.write_all(&(payload.len() as u64).to_le_bytes())?;
+    tmp_file.write_all(&digest)?;
+    tmp_file.flush()?;
+    drop(tmp_file);
+
+    fs::rename(&tmp_path, &sidecar_path)?;
+    Ok(())
+}
+
+/// Verifies `path` against the `.integrity` sidecar file written by [`write_integrity_sidecar`]:
+/// the magic matches, the recorded length matches `path`'s size, and the recorded digest matches
+/// `path`'s contents. A missing sidecar is reported via [`Error::Unverified`], same as a missing
+/// inline header in [`verify_trace_file`].
+pub fn verify_trace_sidecar(path: &Path) -> Result<(), Error> {
+    let sidecar_path = sidecar_path(path);
+    let header = fs::read(&sidecar_path)
+        .map_err(|_| Error::Unverified(format!("{sidecar_path:?} has no integrity sidecar")))?;
+
+    if header.len() != HEADER_SIZE {
+        return Err(Error::Unverified(format!(
+            "{sidecar_path:?} is not a valid integrity sidecar"
+        )));
+    }
+
+    let (magic, rest) = header.split_at(MAGIC.len());
+    if magic != MAGIC {
+        return Err(Error::Unverified(format!(
+            "{sidecar_path:?} does not start with the expected integrity header magic"
+        )));
+    }
+
+    let (length_bytes, digest_bytes) = rest.split_at(LENGTH_SIZE);
+    let expected_len = u64::from_le_bytes(length_bytes.try_into().unwrap()) as usize;
+
+    let payload = fs::read(path)?;
+    if payload.len() != expected_len {
+        return Err(Error::IntegrityError(format!(
+            "{path:?} has length {} but its sidecar records {expected_len}",
+            payload.len()
+        )));
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(&payload);
+    let actual_digest = hasher.finalize();
+
+    if actual_digest.as_slice() != digest_bytes {
+        return Err(Error::IntegrityError(format!(
+            "{path:?} failed its SHA-256 checksum"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Strips a previously-written integrity header from `path`, leaving a bare trace file behind
+/// for tools (e.g. `trace_processor`) that expect a plain protobuf stream.
+pub fn strip_integrity_header(path: &Path) -> Result<(), Error> {
+    let data = fs::read(path)?;
+
+    if data.len() < HEADER_SIZE || data[..MAGIC.len()] != MAGIC {
+        return Err(Error::IntegrityError(format!(
+            "{path:?} does not carry an integrity header to strip"
+        )));
+    }
+
+    let payload = &data[HEADER_SIZE..];
+
+    let tmp_path = path.with_extension("integrity-tmp");
+    let mut tmp_file = File::create(&tmp_path)?;
+    tmp_file.write_all(payload)?;
+    tmp_file.flush()?;
+    drop(tmp_file);
+
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
@@ -4,8 +4,17 @@ mod counter;
 mod error;
 mod event;
 mod guard;
+mod integrity;
+mod track;
+mod trace_config;
 
 pub use counter::{set_counter_f64, set_counter_u64};
 pub use error::Error;
-pub use event::{create_instant_event, EventData, TraceEvent};
-pub use guard::{BackendConfig, PerfettoGuard};
+pub use event::{create_counter_event, create_instant_event, EventData, StaticString, TraceEvent};
+pub use guard::{BackendConfig, IntegrityMode, PerfettoGuard};
+pub use integrity::{
+    strip_integrity_header, verify_trace_file, verify_trace_sidecar, write_integrity_header,
+    write_integrity_sidecar,
+};
+pub use track::{set_counter_track_name, set_track_name, TrackParent};
+pub use trace_config::{BufferId, FillPolicy, TraceConfig, TraceConfigBuilder};
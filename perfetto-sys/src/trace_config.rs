@@ -0,0 +1,339 @@
+// Copyright 2024-2025 Irreducible Inc.
+
+//! Builds a Perfetto `TraceConfig`: one or more named buffers with independent sizes and fill
+//! policies, a mapping from data source to buffer, and the periodic flush/clear-state intervals.
+//! [`TraceConfig`] renders this into the two wire formats perfetto-sys's backends need: a
+//! serialized binary protobuf for the in-process FFI backend (see [`crate::PerfettoGuard`]), and
+//! the `--txt` text-proto format the `perfetto` CLI reads for the system backend.
+//!
+//! Field numbers below are taken from perfetto's `trace_config.proto` and its nested
+//! `TraceConfig.BufferConfig`/`TraceConfig.DataSource`/`DataSourceConfig` messages.
+
+use std::time::Duration;
+
+const WIRE_VARINT: u8 = 0;
+const WIRE_LEN: u8 = 2;
+
+const FIELD_TRACE_CONFIG_BUFFERS: u32 = 1;
+const FIELD_TRACE_CONFIG_DATA_SOURCES: u32 = 2;
+const FIELD_TRACE_CONFIG_FLUSH_PERIOD_MS: u32 = 13;
+const FIELD_TRACE_CONFIG_INCREMENTAL_STATE_CONFIG: u32 = 21;
+
+const FIELD_BUFFER_CONFIG_SIZE_KB: u32 = 1;
+const FIELD_BUFFER_CONFIG_FILL_POLICY: u32 = 4;
+
+const FIELD_TRACE_CONFIG_DATA_SOURCE_CONFIG: u32 = 1; // TraceConfig.DataSource.config
+const FIELD_DATA_SOURCE_CONFIG_NAME: u32 = 1; // DataSourceConfig.name
+const FIELD_DATA_SOURCE_CONFIG_TARGET_BUFFER: u32 = 2; // DataSourceConfig.target_buffer
+const FIELD_DATA_SOURCE_CONFIG_TRACK_EVENT_CONFIG: u32 = 113; // DataSourceConfig.track_event_config
+
+const FIELD_TRACK_EVENT_CONFIG_DISABLED_CATEGORIES: u32 = 1;
+const FIELD_TRACK_EVENT_CONFIG_ENABLED_CATEGORIES: u32 = 2;
+
+const FIELD_INCREMENTAL_STATE_CONFIG_CLEAR_PERIOD_MS: u32 = 1;
+
+const FILL_POLICY_RING_BUFFER: u64 = 1;
+const FILL_POLICY_DISCARD: u64 = 2;
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+fn write_tag(buf: &mut Vec<u8>, field: u32, wire_type: u8) {
+    write_varint(buf, ((field as u64) << 3) | wire_type as u64);
+}
+
+fn write_len_delimited(buf: &mut Vec<u8>, field: u32, nested: &[u8]) {
+    write_tag(buf, field, WIRE_LEN);
+    write_varint(buf, nested.len() as u64);
+    buf.extend_from_slice(nested);
+}
+
+fn write_string(buf: &mut Vec<u8>, field: u32, value: &str) {
+    write_len_delimited(buf, field, value.as_bytes());
+}
+
+fn write_u32(buf: &mut Vec<u8>, field: u32, value: u32) {
+    write_tag(buf, field, WIRE_VARINT);
+    write_varint(buf, value as u64);
+}
+
+/// How a buffer behaves once it fills up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillPolicy {
+    /// Overwrite the oldest events once the buffer is full.
+    RingBuffer,
+    /// Stop accepting new events once the buffer is full.
+    Discard,
+}
+
+impl FillPolicy {
+    fn proto_value(self) -> u64 {
+        match self {
+            FillPolicy::RingBuffer => FILL_POLICY_RING_BUFFER,
+            FillPolicy::Discard => FILL_POLICY_DISCARD,
+        }
+    }
+
+    fn text_proto_name(self) -> &'static str {
+        match self {
+            FillPolicy::RingBuffer => "RING_BUFFER",
+            FillPolicy::Discard => "DISCARD",
+        }
+    }
+}
+
+/// Identifies a buffer declared via [`TraceConfigBuilder::add_buffer`], so a data source can
+/// later be routed onto it with [`TraceConfigBuilder::map_data_source`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferId(usize);
+
+struct BufferSpec {
+    size_kb: usize,
+    fill_policy: FillPolicy,
+}
+
+struct DataSourceSpec {
+    name: String,
+    target_buffer: usize,
+}
+
+/// Builds a [`TraceConfig`] out of multiple independently-sized, independently-policied buffers,
+/// replacing perfetto-sys's previous single `buffer_size_kb` knob.
+pub struct TraceConfigBuilder {
+    buffers: Vec<BufferSpec>,
+    data_sources: Vec<DataSourceSpec>,
+    flush_period: Duration,
+    clear_incremental_state_period: Option<Duration>,
+    enabled_categories: Vec<String>,
+    disabled_categories: Vec<String>,
+}
+
+impl TraceConfigBuilder {
+    pub fn new() -> Self {
+        Self {
+            buffers: Vec::new(),
+            data_sources: Vec::new(),
+            flush_period: Duration::from_secs(2),
+            clear_incremental_state_period: None,
+            enabled_categories: Vec::new(),
+            disabled_categories: Vec::new(),
+        }
+    }
+
+    /// Declares a new buffer of `size_kb` kilobytes with the given fill policy, returning a
+    /// [`BufferId`] that [`map_data_source`](Self::map_data_source) can target.
+    pub fn add_buffer(&mut self, size_kb: usize, fill_policy: FillPolicy) -> BufferId {
+        self.buffers.push(BufferSpec { size_kb, fill_policy });
+        BufferId(self.buffers.len() - 1)
+    }
+
+    /// Routes the named data source (e.g. `"track_event"`) onto `buffer`.
+    pub fn map_data_source(&mut self, data_source_name: &str, buffer: BufferId) -> &mut Self {
+        self.data_sources.push(DataSourceSpec {
+            name: data_source_name.to_string(),
+            target_buffer: buffer.0,
+        });
+        self
+    }
+
+    /// Sets how often buffered events are flushed to the trace file. Also determines how long
+    /// [`PerfettoGuard`](crate::PerfettoGuard)'s drop waits before tearing the session down, so
+    /// events from just before shutdown aren't lost.
+    pub fn flush_period(&mut self, period: Duration) -> &mut Self {
+        self.flush_period = period;
+        self
+    }
+
+    /// Sets how often incremental state (interned names, track descriptors) is cleared and
+    /// re-emitted, bounding how far back a reader must scan to resolve a given packet. Left unset
+    /// by default, matching perfetto's own default of never clearing.
+    pub fn clear_incremental_state_period(&mut self, period: Duration) -> &mut Self {
+        self.clear_incremental_state_period = Some(period);
+        self
+    }
+
+    /// Enables the given category glob (e.g. `"render_*"`), applied to every data source this
+    /// config declares. Explicit disables via [`disable_category`](Self::disable_category) take
+    /// precedence over enables, matching perfetto's own `TrackEventConfig` semantics.
+    pub fn enable_category(&mut self, pattern: &str) -> &mut Self {
+        self.enabled_categories.push(pattern.to_string());
+        self
+    }
+
+    /// Disables the given category glob, overriding any matching
+    /// [`enable_category`](Self::enable_category).
+    pub fn disable_category(&mut self, pattern: &str) -> &mut Self {
+        self.disabled_categories.push(pattern.to_string());
+        self
+    }
+
+    /// Renders the declared buffers, data sources and flush/clear periods into both wire formats
+    /// perfetto-sys needs.
+    pub fn build(&self) -> TraceConfig {
+        TraceConfig {
+            flush_period: self.flush_period,
+            serialized: self.encode_binary(),
+            text_proto: self.encode_text_proto(),
+        }
+    }
+
+    fn encode_binary(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        for buffer in &self.buffers {
+            let mut nested = Vec::new();
+            write_u32(&mut nested, FIELD_BUFFER_CONFIG_SIZE_KB, buffer.size_kb as u32);
+            write_tag(&mut nested, FIELD_BUFFER_CONFIG_FILL_POLICY, WIRE_VARINT);
+            write_varint(&mut nested, buffer.fill_policy.proto_value());
+            write_len_delimited(&mut buf, FIELD_TRACE_CONFIG_BUFFERS, &nested);
+        }
+
+        let track_event_config = self.encode_track_event_config_binary();
+
+        for data_source in &self.data_sources {
+            let mut config = Vec::new();
+            write_string(&mut config, FIELD_DATA_SOURCE_CONFIG_NAME, &data_source.name);
+            write_u32(
+                &mut config,
+                FIELD_DATA_SOURCE_CONFIG_TARGET_BUFFER,
+                data_source.target_buffer as u32,
+            );
+            if let Some(track_event_config) = &track_event_config {
+                write_len_delimited(
+                    &mut config,
+                    FIELD_DATA_SOURCE_CONFIG_TRACK_EVENT_CONFIG,
+                    track_event_config,
+                );
+            }
+
+            let mut nested = Vec::new();
+            write_len_delimited(&mut nested, FIELD_TRACE_CONFIG_DATA_SOURCE_CONFIG, &config);
+            write_len_delimited(&mut buf, FIELD_TRACE_CONFIG_DATA_SOURCES, &nested);
+        }
+
+        write_u32(
+            &mut buf,
+            FIELD_TRACE_CONFIG_FLUSH_PERIOD_MS,
+            self.flush_period.as_millis() as u32,
+        );
+
+        if let Some(clear_period) = self.clear_incremental_state_period {
+            let mut nested = Vec::new();
+            write_u32(
+                &mut nested,
+                FIELD_INCREMENTAL_STATE_CONFIG_CLEAR_PERIOD_MS,
+                clear_period.as_millis() as u32,
+            );
+            write_len_delimited(&mut buf, FIELD_TRACE_CONFIG_INCREMENTAL_STATE_CONFIG, &nested);
+        }
+
+        buf
+    }
+
+    /// Renders the enabled/disabled category globs into a `TrackEventConfig` message, or `None` if
+    /// neither list has any entries, so a config with no category filtering omits the field
+    /// entirely rather than emitting an empty nested message.
+    fn encode_track_event_config_binary(&self) -> Option<Vec<u8>> {
+        if self.enabled_categories.is_empty() && self.disabled_categories.is_empty() {
+            return None;
+        }
+
+        let mut nested = Vec::new();
+        for category in &self.disabled_categories {
+            write_string(&mut nested, FIELD_TRACK_EVENT_CONFIG_DISABLED_CATEGORIES, category);
+        }
+        for category in &self.enabled_categories {
+            write_string(&mut nested, FIELD_TRACK_EVENT_CONFIG_ENABLED_CATEGORIES, category);
+        }
+        Some(nested)
+    }
+
+    fn encode_text_proto(&self) -> String {
+        let mut out = String::new();
+
+        for buffer in &self.buffers {
+            out.push_str("buffers: {\n");
+            out.push_str(&format!("    size_kb: {}\n", buffer.size_kb));
+            out.push_str(&format!("    fill_policy: {}\n", buffer.fill_policy.text_proto_name()));
+            out.push_str("}\n");
+        }
+
+        for data_source in &self.data_sources {
+            out.push_str("data_sources: {\n    config {\n");
+            out.push_str(&format!("        name: \"{}\"\n", data_source.name));
+            out.push_str(&format!("        target_buffer: {}\n", data_source.target_buffer));
+            if !self.enabled_categories.is_empty() || !self.disabled_categories.is_empty() {
+                out.push_str("        track_event_config {\n");
+                for category in &self.disabled_categories {
+                    out.push_str(&format!("            disabled_categories: \"{category}\"\n"));
+                }
+                for category in &self.enabled_categories {
+                    out.push_str(&format!("            enabled_categories: \"{category}\"\n"));
+                }
+                out.push_str("        }\n");
+            }
+            out.push_str("    }\n}\n");
+        }
+
+        out.push_str(&format!("flush_period_ms: {}\n", self.flush_period.as_millis()));
+
+        if let Some(clear_period) = self.clear_incremental_state_period {
+            out.push_str("incremental_state_config: {\n");
+            out.push_str(&format!("    clear_period_ms: {}\n", clear_period.as_millis()));
+            out.push_str("}\n");
+        }
+
+        out
+    }
+}
+
+impl Default for TraceConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A fully-rendered trace configuration, in both encodings perfetto-sys's two backends need.
+/// Build one with [`TraceConfigBuilder`], or use [`TraceConfig::single_buffer`] for the common
+/// single-buffer case.
+#[derive(Debug, Clone)]
+pub struct TraceConfig {
+    flush_period: Duration,
+    serialized: Vec<u8>,
+    text_proto: String,
+}
+
+impl TraceConfig {
+    /// A single `buffer_size_kb`-kilobyte ring buffer feeding the `track_event` data source,
+    /// matching perfetto-sys's previous single-buffer default.
+    pub fn single_buffer(buffer_size_kb: usize) -> Self {
+        let mut builder = TraceConfigBuilder::new();
+        let buffer = builder.add_buffer(buffer_size_kb, FillPolicy::RingBuffer);
+        builder.map_data_source("track_event", buffer);
+        builder.build()
+    }
+
+    /// How often buffered events are flushed; also determines how long
+    /// [`PerfettoGuard`](crate::PerfettoGuard)'s drop waits for the last flush before tearing the
+    /// session down.
+    pub fn flush_period(&self) -> Duration {
+        self.flush_period
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.serialized
+    }
+
+    pub(crate) fn as_text_proto(&self) -> &str {
+        &self.text_proto
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright 2024-2025 Irreducible Inc.
+
+use std::{
+    collections::HashMap,
+    ffi::{c_char, CString},
+    sync::{Mutex, OnceLock},
+};
+
+#[repr(u8)]
+enum TrackParentKind {
+    None = 0,
+    Process = 1,
+    Thread = 2,
+}
+
+extern "C" {
+    fn set_track_descriptor(
+        uuid: u64,
+        name: *const c_char,
+        parent_kind: TrackParentKind,
+        parent_thread_id: u64,
+    );
+    fn set_counter_track_descriptor(uuid: u64, name: *const c_char);
+}
+
+/// Where a named async track should be anchored in Perfetto's UI, mirroring the SDK's own track
+/// model: nested under the current process, under a specific OS thread, or standalone.
+#[derive(Debug, Clone, Copy)]
+pub enum TrackParent {
+    Process,
+    Thread(u64),
+    None,
+}
+
+/// Registers `name` for `uuid` in `registry`, the first time this uuid is seen. Returns whether
+/// the caller should go on to emit the uuid's `TrackDescriptor`. A `uuid` reused with a different
+/// `name` is a caller bug (track uuids must be unique across the whole trace), so that's reported
+/// to stderr and the original name is kept.
+fn register_track_name(registry: &Mutex<HashMap<u64, String>>, uuid: u64, name: &str) -> bool {
+    let mut registry = registry.lock().unwrap();
+    match registry.get(&uuid) {
+        Some(existing) if existing != name => {
+            eprintln!(
+                "perfetto track {uuid} is already named {existing:?}; ignoring conflicting name {name:?}"
+            );
+            false
+        }
+        Some(_) => false,
+        None => {
+            registry.insert(uuid, name.to_string());
+            true
+        }
+    }
+}
+
+/// Emits a `TrackDescriptor` packet naming `uuid`, the first time this uuid is seen. Perfetto
+/// only needs (and only wants) one descriptor per track for the life of the trace, so repeat
+/// calls for an already-named uuid are no-ops; this lets callers name a track on every span that
+/// uses it without re-emitting the descriptor on every one.
+pub fn set_track_name(uuid: u64, name: &str, parent: TrackParent) {
+    static NAMED_TRACKS: OnceLock<Mutex<HashMap<u64, String>>> = OnceLock::new();
+    let named_tracks = NAMED_TRACKS.get_or_init(|| Mutex::new(HashMap::new()));
+
+    if !register_track_name(named_tracks, uuid, name) {
+        return;
+    }
+
+    let name = CString::new(name).expect("track name is not a valid string");
+    let (parent_kind, parent_thread_id) = match parent {
+        TrackParent::Process => (TrackParentKind::Process, 0),
+        TrackParent::Thread(thread_id) => (TrackParentKind::Thread, thread_id),
+        TrackParent::None => (TrackParentKind::None, 0),
+    };
+
+    unsafe { set_track_descriptor(uuid, name.as_ptr(), parent_kind, parent_thread_id) };
+}
+
+/// Emits a counter `TrackDescriptor` naming `uuid`, the first time this uuid is seen, so
+/// [`crate::create_counter_event`] samples on it render as a named, continuous line in the
+/// Perfetto UI rather than an anonymous track.
+pub fn set_counter_track_name(uuid: u64, name: &str) {
+    static NAMED_COUNTER_TRACKS: OnceLock<Mutex<HashMap<u64, String>>> = OnceLock::new();
+    let named_tracks = NAMED_COUNTER_TRACKS.get_or_init(|| Mutex::new(HashMap::new()));
+
+    if !register_track_name(named_tracks, uuid, name) {
+        return;
+    }
+
+    let name = CString::new(name).expect("track name is not a valid string");
+    unsafe { set_counter_track_descriptor(uuid, name.as_ptr()) };
+}
@@ -0,0 +1,90 @@
+// Copyright 2024-2025 Irreducible Inc.
+
+//! Optional per-span allocation accounting, inspired by rust-analyzer's `memory_usage.rs`.
+//!
+//! Wrap the process's real allocator in [`CountingAllocator`] and declare it as the
+//! `#[global_allocator]`; `layers::graph::Layer` (when `Config::enable_alloc_counters` is set)
+//! reads the thread-local running totals in `on_enter`/`on_exit` to report bytes allocated and
+//! allocation count per span.
+//!
+//! ```ignore
+//! use std::alloc::System;
+//! use tracing_profile::CountingAllocator;
+//!
+//! #[global_allocator]
+//! static ALLOC: CountingAllocator<System> = CountingAllocator::new(System);
+//! ```
+
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    cell::Cell,
+};
+
+thread_local! {
+    static BYTES: Cell<u64> = const { Cell::new(0) };
+    static ALLOCS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// A snapshot (or, once subtracted, a delta) of this thread's running allocation totals.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AllocCounters {
+    pub bytes: u64,
+    pub allocs: u64,
+}
+
+impl std::ops::Sub for AllocCounters {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            bytes: self.bytes.saturating_sub(rhs.bytes),
+            allocs: self.allocs.saturating_sub(rhs.allocs),
+        }
+    }
+}
+
+impl std::ops::AddAssign for AllocCounters {
+    fn add_assign(&mut self, rhs: Self) {
+        self.bytes += rhs.bytes;
+        self.allocs += rhs.allocs;
+    }
+}
+
+/// Reads the current thread's running allocation totals.
+pub fn read() -> AllocCounters {
+    AllocCounters {
+        bytes: BYTES.with(Cell::get),
+        allocs: ALLOCS.with(Cell::get),
+    }
+}
+
+/// A [`GlobalAlloc`] wrapper that keeps a thread-local running total of bytes allocated and
+/// allocation count, on top of delegating every call to the wrapped allocator `A`.
+pub struct CountingAllocator<A>(A);
+
+impl<A> CountingAllocator<A> {
+    pub const fn new(inner: A) -> Self {
+        Self(inner)
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        BYTES.with(|bytes| bytes.set(bytes.get() + layout.size() as u64));
+        ALLOCS.with(|allocs| allocs.set(allocs.get() + 1));
+
+        self.0.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size > layout.size() {
+            BYTES.with(|bytes| bytes.set(bytes.get() + (new_size - layout.size()) as u64));
+        }
+
+        self.0.realloc(ptr, layout, new_size)
+    }
+}
@@ -0,0 +1,262 @@
+// Copyright 2024-2025 Irreducible Inc.
+
+//! A compact binary alternative to [`WritingFieldVisitor`](super::WritingFieldVisitor).
+//!
+//! `WritingFieldVisitor` produces human-readable `key: value` text, which is large and slow to
+//! parse back when profiling high-frequency spans. `BinaryFieldVisitor` instead serializes each
+//! field into a self-describing binary buffer: a type tag, the field name as a length-prefixed
+//! string, then the payload. Signed integers are zig-zag varint encoded, `u64` is a plain
+//! varint, `f64` is 8 raw bytes, `bool` is a single byte, and strings/debug output are
+//! length-prefixed UTF-8. [`read_binary_fields`] walks the buffer back into `(name, value)`
+//! pairs without needing a schema.
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tag {
+    I64 = 0,
+    U64 = 1,
+    F64 = 2,
+    Bool = 3,
+    Str = 4,
+}
+
+impl Tag {
+    fn from_u8(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(Tag::I64),
+            1 => Some(Tag::U64),
+            2 => Some(Tag::F64),
+            3 => Some(Tag::Bool),
+            4 => Some(Tag::Str),
+            _ => None,
+        }
+    }
+}
+
+/// A field value decoded by [`read_binary_fields`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinaryFieldValue {
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Bool(bool),
+    Str(String),
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+fn read_varint(buf: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *buf.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
+
+fn zigzag_encode(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn zigzag_decode(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+fn write_name(buf: &mut Vec<u8>, name: &str) {
+    write_varint(buf, name.len() as u64);
+    buf.extend_from_slice(name.as_bytes());
+}
+
+fn read_name(buf: &[u8], pos: &mut usize) -> Option<String> {
+    let len = read_varint(buf, pos)? as usize;
+    let bytes = buf.get(*pos..*pos + len)?;
+    *pos += len;
+    Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Serializes tracing field records into a compact, self-describing binary buffer.
+pub struct BinaryFieldVisitor<'a> {
+    buffer: &'a mut Vec<u8>,
+}
+
+impl<'a> BinaryFieldVisitor<'a> {
+    pub fn new(buffer: &'a mut Vec<u8>) -> Self {
+        Self { buffer }
+    }
+
+    fn write_str_payload(&mut self, tag: Tag, field: &tracing::field::Field, value: &str) {
+        self.buffer.push(tag as u8);
+        write_name(self.buffer, field.name());
+        write_varint(self.buffer, value.len() as u64);
+        self.buffer.extend_from_slice(value.as_bytes());
+    }
+}
+
+impl tracing::field::Visit for BinaryFieldVisitor<'_> {
+    fn record_i64(&mut self, field: &tracing::field::Field, value: i64) {
+        self.buffer.push(Tag::I64 as u8);
+        write_name(self.buffer, field.name());
+        write_varint(self.buffer, zigzag_encode(value));
+    }
+
+    fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+        self.buffer.push(Tag::U64 as u8);
+        write_name(self.buffer, field.name());
+        write_varint(self.buffer, value);
+    }
+
+    fn record_f64(&mut self, field: &tracing::field::Field, value: f64) {
+        self.buffer.push(Tag::F64 as u8);
+        write_name(self.buffer, field.name());
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn record_bool(&mut self, field: &tracing::field::Field, value: bool) {
+        self.buffer.push(Tag::Bool as u8);
+        write_name(self.buffer, field.name());
+        self.buffer.push(value as u8);
+    }
+
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        self.write_str_payload(Tag::Str, field, value);
+    }
+
+    fn record_error(
+        &mut self,
+        field: &tracing::field::Field,
+        value: &(dyn std::error::Error + 'static),
+    ) {
+        self.write_str_payload(Tag::Str, field, &value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        self.write_str_payload(Tag::Str, field, &format!("{:?}", value));
+    }
+}
+
+/// Decode a buffer produced by [`BinaryFieldVisitor`] back into `(name, value)` pairs.
+///
+/// Returns `None` if the buffer is truncated or contains an unrecognized tag.
+pub fn read_binary_fields(buf: &[u8]) -> Option<Vec<(String, BinaryFieldValue)>> {
+    let mut pos = 0;
+    let mut fields = Vec::new();
+
+    while pos < buf.len() {
+        let tag = Tag::from_u8(*buf.get(pos)?)?;
+        pos += 1;
+        let name = read_name(buf, &mut pos)?;
+
+        let value = match tag {
+            Tag::I64 => BinaryFieldValue::I64(zigzag_decode(read_varint(buf, &mut pos)?)),
+            Tag::U64 => BinaryFieldValue::U64(read_varint(buf, &mut pos)?),
+            Tag::F64 => {
+                let bytes = buf.get(pos..pos + 8)?;
+                pos += 8;
+                BinaryFieldValue::F64(f64::from_le_bytes(bytes.try_into().ok()?))
+            }
+            Tag::Bool => {
+                let byte = *buf.get(pos)?;
+                pos += 1;
+                BinaryFieldValue::Bool(byte != 0)
+            }
+            Tag::Str => {
+                let len = read_varint(buf, &mut pos)? as usize;
+                let bytes = buf.get(pos..pos + len)?;
+                pos += len;
+                BinaryFieldValue::Str(String::from_utf8_lossy(bytes).into_owned())
+            }
+        };
+
+        fields.push((name, value));
+    }
+
+    Some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::{span, Event, Metadata, Subscriber};
+
+    use super::*;
+
+    /// Minimal subscriber that records every event's fields through a `BinaryFieldVisitor`.
+    struct CapturingSubscriber(Arc<Mutex<Vec<u8>>>);
+
+    impl Subscriber for CapturingSubscriber {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+        fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut buffer = self.0.lock().unwrap();
+            let mut visitor = BinaryFieldVisitor::new(&mut buffer);
+            event.record(&mut visitor);
+        }
+
+        fn enter(&self, _span: &span::Id) {}
+        fn exit(&self, _span: &span::Id) {}
+    }
+
+    #[test]
+    fn round_trips_scalar_fields() {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = CapturingSubscriber(buffer.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(
+                an_i64 = -5i64,
+                a_u64 = 5u64,
+                a_f64 = 1.5,
+                a_bool = true,
+                a_str = "hello"
+            );
+        });
+
+        let buf = buffer.lock().unwrap();
+        let fields = read_binary_fields(&buf).unwrap();
+        assert_eq!(
+            fields,
+            vec![
+                ("an_i64".to_string(), BinaryFieldValue::I64(-5)),
+                ("a_u64".to_string(), BinaryFieldValue::U64(5)),
+                ("a_f64".to_string(), BinaryFieldValue::F64(1.5)),
+                ("a_bool".to_string(), BinaryFieldValue::Bool(true)),
+                (
+                    "a_str".to_string(),
+                    BinaryFieldValue::Str("hello".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn zigzag_round_trips_negative_and_positive() {
+        for value in [-1, 0, 1, i64::MIN, i64::MAX] {
+            assert_eq!(zigzag_decode(zigzag_encode(value)), value);
+        }
+    }
+}
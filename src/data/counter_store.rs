@@ -0,0 +1,144 @@
+// Copyright 2024-2025 Irreducible Inc.
+
+use std::collections::HashMap;
+
+use super::field_visitor::{CounterStats, CounterValue};
+
+/// Key identifying a single counter series: its name plus optional category.
+pub type CounterKey = (String, Option<String>);
+
+/// Maintains running totals for counters produced by [`CounterVisitor`](super::CounterVisitor),
+/// keyed by `(name, category)`.
+///
+/// Incremental samples (`is_incremental = true`) are added into the running total; absolute
+/// samples overwrite it outright. Call [`snapshot`](Self::snapshot) to get the current absolute
+/// value of every series, e.g. for periodic emission to a downstream backend.
+///
+/// Independently of that total, every sample's raw value also folds into a [`CounterStats`],
+/// regardless of `is_incremental` — this tracks the distribution of individual samples (mean,
+/// variance, min, max), which the running total alone can't show. Call
+/// [`stats`](Self::stats)/[`stats_snapshot`](Self::stats_snapshot) to read it.
+#[derive(Default, Debug, Clone)]
+pub struct CounterStore {
+    totals: HashMap<CounterKey, CounterValue>,
+    stats: HashMap<CounterKey, CounterStats>,
+}
+
+impl CounterStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a sample for the given series, accumulating if `is_incremental`, otherwise
+    /// overwriting the running total.
+    pub fn record(
+        &mut self,
+        name: &str,
+        category: Option<&str>,
+        is_incremental: bool,
+        value: CounterValue,
+    ) {
+        let key = (name.to_string(), category.map(str::to_string));
+        if is_incremental {
+            match self.totals.get_mut(&key) {
+                Some(total) => *total += value,
+                None => {
+                    self.totals.insert(key.clone(), value);
+                }
+            }
+        } else {
+            self.totals.insert(key.clone(), value);
+        }
+
+        match self.stats.get_mut(&key) {
+            Some(stats) => stats.record(value),
+            None => {
+                self.stats.insert(key, CounterStats::new(value));
+            }
+        }
+    }
+
+    /// Look up the current absolute value of a single series.
+    pub fn get(&self, name: &str, category: Option<&str>) -> Option<CounterValue> {
+        self.totals
+            .get(&(name.to_string(), category.map(str::to_string)))
+            .copied()
+    }
+
+    /// Returns the current absolute value of every series.
+    pub fn snapshot(&self) -> Vec<(CounterKey, CounterValue)> {
+        self.totals.iter().map(|(k, v)| (k.clone(), *v)).collect()
+    }
+
+    /// Look up the running distribution statistics (mean/variance/min/max) of a single series.
+    pub fn stats(&self, name: &str, category: Option<&str>) -> Option<CounterStats> {
+        self.stats
+            .get(&(name.to_string(), category.map(str::to_string)))
+            .copied()
+    }
+
+    /// Returns the running distribution statistics of every series.
+    pub fn stats_snapshot(&self) -> Vec<(CounterKey, CounterStats)> {
+        self.stats.iter().map(|(k, v)| (k.clone(), *v)).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.totals.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.totals.clear();
+        self.stats.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn incremental_samples_accumulate() {
+        let mut store = CounterStore::new();
+        store.record("proof_size", None, true, CounterValue::Int(10));
+        store.record("proof_size", None, true, CounterValue::Int(5));
+
+        assert_eq!(store.get("proof_size", None), Some(CounterValue::Int(15)));
+    }
+
+    #[test]
+    fn absolute_samples_overwrite() {
+        let mut store = CounterStore::new();
+        store.record("queue_depth", None, false, CounterValue::Int(10));
+        store.record("queue_depth", None, false, CounterValue::Int(3));
+
+        assert_eq!(store.get("queue_depth", None), Some(CounterValue::Int(3)));
+    }
+
+    #[test]
+    fn categories_are_tracked_independently() {
+        let mut store = CounterStore::new();
+        store.record("reads", Some("io"), true, CounterValue::Int(1));
+        store.record("reads", Some("net"), true, CounterValue::Int(2));
+
+        assert_eq!(store.get("reads", Some("io")), Some(CounterValue::Int(1)));
+        assert_eq!(store.get("reads", Some("net")), Some(CounterValue::Int(2)));
+    }
+
+    #[test]
+    fn stats_track_distribution_independently_of_the_running_total() {
+        let mut store = CounterStore::new();
+        store.record("latency_ms", None, false, CounterValue::Int(10));
+        store.record("latency_ms", None, false, CounterValue::Int(20));
+        store.record("latency_ms", None, false, CounterValue::Int(30));
+
+        // the absolute samples overwrite each other, but stats sees every one of them.
+        assert_eq!(store.get("latency_ms", None), Some(CounterValue::Int(30)));
+
+        let stats = store.stats("latency_ms", None).unwrap();
+        assert_eq!(stats.count(), 3);
+        assert_eq!(stats.mean(), 20.0);
+        assert_eq!(stats.min(), 10.0);
+        assert_eq!(stats.max(), 30.0);
+        assert!((stats.variance() - 200.0 / 3.0).abs() < 1e-9);
+    }
+}
@@ -2,7 +2,7 @@
 
 use crate::errors::err_msg;
 
-use super::field_visitor::{CounterValue, CounterVisitor};
+use super::field_visitor::{CounterValue, CounterVisitor, CounterVisitorConfig};
 use super::WritingFieldVisitor;
 use linear_map::LinearMap;
 use std::fmt::Write;
@@ -19,6 +19,9 @@ pub(crate) struct EventCounts {
 impl EventCounts {
     /// Record a new event.
     pub fn record(&mut self, event: &tracing::Event<'_>) {
+        #[cfg(feature = "metatrace")]
+        let _timer = crate::metatrace::Timer::start("event_counts::record");
+
         if !event.fields().any(|_| true) {
             // If no fields we can just use the event name as a key.
             let name = Cow::Borrowed(event.metadata().name());
@@ -29,20 +32,37 @@ impl EventCounts {
                 }
             }
         } else {
-            let mut data = CounterVisitor::default();
+            // `multi_series` is enabled here so a counter event with several numeric fields
+            // (e.g. `counter!(reads = 10, writes = 3)`) records one series per field instead of
+            // only ever tracking the single `value` field.
+            let mut data = CounterVisitor::new(CounterVisitorConfig {
+                multi_series: true,
+                ..Default::default()
+            });
             event.record(&mut data);
 
             if data.is_counter {
-                match (self.counters.get_mut(event.metadata().name()), data.value) {
-                    (None, Some(new_value)) => {
-                        let name = Cow::Borrowed(event.metadata().name());
-                        self.counters.insert(name, new_value);
-                    }
-                    (Some(value), Some(new_value)) => *value += new_value,
-                    _ => {
-                        err_msg!("invalid event {:?}", event);
+                if !data.series.is_empty() {
+                    for (name, new_value) in data.series {
+                        match self.counters.get_mut(&name) {
+                            Some(value) => *value += new_value,
+                            None => {
+                                self.counters.insert(name, new_value);
+                            }
+                        }
                     }
-                };
+                } else {
+                    match (self.counters.get_mut(event.metadata().name()), data.value) {
+                        (None, Some(new_value)) => {
+                            let name = Cow::Borrowed(event.metadata().name());
+                            self.counters.insert(name, new_value);
+                        }
+                        (Some(value), Some(new_value)) => *value += new_value,
+                        _ => {
+                            err_msg!("invalid event {:?}", event);
+                        }
+                    };
+                }
             } else {
                 // If events are generating frequently in most of the cases we will be incrementing the counter
                 // for already allocated string key. So, we can reuse the buffer and avoid reallocation.
@@ -100,6 +120,80 @@ impl EventCounts {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    /// Forwards every event straight into a shared `EventCounts`, so `record` can be exercised
+    /// through a real `tracing::Event` (which can only be constructed via dispatch) instead of
+    /// hand-building one.
+    struct CaptureLayer(Arc<Mutex<EventCounts>>);
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for CaptureLayer {
+        fn on_event(
+            &self,
+            event: &tracing::Event<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            self.0.lock().unwrap().record(event);
+        }
+    }
+
+    #[test]
+    fn multi_series_event_records_one_series_per_field() {
+        let counts = Arc::new(Mutex::new(EventCounts::default()));
+        let subscriber = tracing_subscriber::registry().with(CaptureLayer(counts.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::event!(name: "io_stats", tracing::Level::INFO, counter = true, reads = 10u64, writes = 3u64);
+        });
+
+        let counts = counts.lock().unwrap();
+        assert_eq!(counts.get("reads"), Some(&CounterValue::Int(10)));
+        assert_eq!(counts.get("writes"), Some(&CounterValue::Int(3)));
+    }
+
+    /// A counter event carrying a structured (`valuable`) payload instead of scalar fields used
+    /// to fall into the `(None, None)` "invalid event" arm of [`EventCounts::record`], since
+    /// `record_value` bailed out unless `multi_series` was on. It now flattens into one series
+    /// entry per numeric leaf.
+    #[test]
+    #[cfg(tracing_unstable)]
+    fn valuable_struct_event_records_one_series_per_leaf() {
+        #[derive(valuable::Valuable)]
+        struct MemStats {
+            heap_bytes: u64,
+            resident_bytes: u64,
+        }
+
+        let counts = Arc::new(Mutex::new(EventCounts::default()));
+        let subscriber = tracing_subscriber::registry().with(CaptureLayer(counts.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::event!(
+                name: "mem_stats",
+                tracing::Level::INFO,
+                counter = true,
+                stats = tracing::field::valuable(&MemStats {
+                    heap_bytes: 4096,
+                    resident_bytes: 8192,
+                })
+            );
+        });
+
+        let counts = counts.lock().unwrap();
+        assert_eq!(counts.get("stats.heap_bytes"), Some(&CounterValue::Int(4096)));
+        assert_eq!(
+            counts.get("stats.resident_bytes"),
+            Some(&CounterValue::Int(8192))
+        );
+    }
+}
+
 impl AddAssign<&EventCounts> for EventCounts {
     fn add_assign(&mut self, rhs: &EventCounts) {
         for (name, count) in &rhs.counters {
@@ -3,6 +3,96 @@
 use std::{borrow::Cow, fmt::Write};
 use {linear_map::LinearMap, std::ops::AddAssign};
 
+/// A flattened leaf value from walking a `valuable::Value`, already converted to an owned form so
+/// the walk doesn't need to thread the source value's borrow through every recursive call.
+#[cfg(tracing_unstable)]
+enum StructuredLeaf {
+    Numeric(CounterValue),
+    Text(String),
+}
+
+/// Walks a `valuable::Value`, flattening nested structs/maps into dotted key paths (e.g.
+/// `mem.heap_bytes`) and calling `on_leaf` with each scalar leaf's path (relative to the value's
+/// own root, i.e. not yet including the field name) and converted value.
+#[cfg(tracing_unstable)]
+fn flatten_valuable(value: valuable::Value<'_>, on_leaf: &mut dyn FnMut(&str, StructuredLeaf)) {
+    struct Flattener<'a> {
+        prefix: String,
+        on_leaf: &'a mut dyn FnMut(&str, StructuredLeaf),
+    }
+
+    impl Flattener<'_> {
+        fn joined(&self, key: &str) -> String {
+            if self.prefix.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", self.prefix, key)
+            }
+        }
+    }
+
+    impl valuable::Visit for Flattener<'_> {
+        fn visit_value(&mut self, value: valuable::Value<'_>) {
+            let leaf = match value {
+                valuable::Value::I8(v) => StructuredLeaf::Numeric(CounterValue::Int(v as _)),
+                valuable::Value::I16(v) => StructuredLeaf::Numeric(CounterValue::Int(v as _)),
+                valuable::Value::I32(v) => StructuredLeaf::Numeric(CounterValue::Int(v as _)),
+                valuable::Value::I64(v) => StructuredLeaf::Numeric(CounterValue::Int(v as _)),
+                valuable::Value::I128(v) => StructuredLeaf::Numeric(CounterValue::Int(v as _)),
+                valuable::Value::Isize(v) => StructuredLeaf::Numeric(CounterValue::Int(v as _)),
+                valuable::Value::U8(v) => StructuredLeaf::Numeric(CounterValue::Int(v as _)),
+                valuable::Value::U16(v) => StructuredLeaf::Numeric(CounterValue::Int(v as _)),
+                valuable::Value::U32(v) => StructuredLeaf::Numeric(CounterValue::Int(v as _)),
+                valuable::Value::U64(v) => StructuredLeaf::Numeric(CounterValue::Int(v)),
+                valuable::Value::U128(v) => StructuredLeaf::Numeric(CounterValue::Int(v as _)),
+                valuable::Value::Usize(v) => StructuredLeaf::Numeric(CounterValue::Int(v as _)),
+                valuable::Value::F32(v) => StructuredLeaf::Numeric(CounterValue::Float(v as _)),
+                valuable::Value::F64(v) => StructuredLeaf::Numeric(CounterValue::Float(v)),
+                other => StructuredLeaf::Text(format!("{other:?}")),
+            };
+            (self.on_leaf)(&self.prefix, leaf);
+        }
+
+        fn visit_named_fields(&mut self, named_values: &valuable::NamedValues<'_>) {
+            for (field, value) in named_values.iter() {
+                let mut child = Flattener {
+                    prefix: self.joined(field.name()),
+                    on_leaf: &mut *self.on_leaf,
+                };
+                value.visit(&mut child);
+            }
+        }
+
+        fn visit_unnamed_fields(&mut self, values: &[valuable::Value<'_>]) {
+            for (index, value) in values.iter().enumerate() {
+                let mut child = Flattener {
+                    prefix: self.joined(&index.to_string()),
+                    on_leaf: &mut *self.on_leaf,
+                };
+                value.visit(&mut child);
+            }
+        }
+
+        fn visit_entry(&mut self, key: valuable::Value<'_>, value: valuable::Value<'_>) {
+            let key = match key {
+                valuable::Value::String(s) => s.to_string(),
+                other => format!("{other:?}"),
+            };
+            let mut child = Flattener {
+                prefix: self.joined(&key),
+                on_leaf: &mut *self.on_leaf,
+            };
+            value.visit(&mut child);
+        }
+    }
+
+    let mut flattener = Flattener {
+        prefix: String::new(),
+        on_leaf,
+    };
+    value.visit(&mut flattener);
+}
+
 pub struct StoringFieldVisitor<'a>(pub &'a mut LinearMap<&'static str, String>);
 
 impl tracing::field::Visit for StoringFieldVisitor<'_> {
@@ -109,6 +199,30 @@ impl<Writer: Write> tracing::field::Visit for WritingFieldVisitor<'_, Writer> {
         self.write_separator();
         write!(self.writer, "{}: {:?}", field.name(), value).expect("failed to write debug");
     }
+
+    /// Structured fields (e.g. a `valuable`-derived struct or map) are flattened into dotted key
+    /// paths instead of collapsing to one `Debug` blob, so `mem = MemStats { heap_bytes: 4096 }`
+    /// renders as `mem.heap_bytes = 4096` rather than `mem: MemStats { heap_bytes: 4096 }`.
+    #[cfg(tracing_unstable)]
+    fn record_value(&mut self, field: &tracing::field::Field, value: valuable::Value<'_>) {
+        let field_name = field.name();
+        flatten_valuable(value, &mut |path, leaf| {
+            self.write_separator();
+            let key = if path.is_empty() {
+                field_name.to_string()
+            } else {
+                format!("{field_name}.{path}")
+            };
+            match leaf {
+                StructuredLeaf::Numeric(value) => {
+                    write!(self.writer, "{key} = {value}").expect("failed to write structured leaf")
+                }
+                StructuredLeaf::Text(value) => {
+                    write!(self.writer, "{key} = {value}").expect("failed to write structured leaf")
+                }
+            }
+        });
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -134,12 +248,18 @@ impl std::fmt::Display for CounterValue {
 
 impl AddAssign for CounterValue {
     fn add_assign(&mut self, rhs: Self) {
-        match (self, rhs) {
-            (CounterValue::Int(lhs), CounterValue::Int(rhs)) => *lhs += rhs,
-            (CounterValue::Int(lhs), CounterValue::Float(rhs)) => *lhs += rhs as u64,
-            (CounterValue::Float(lhs), CounterValue::Int(rhs)) => *lhs += rhs as f64,
-            (CounterValue::Float(lhs), CounterValue::Float(rhs)) => *lhs += rhs,
-        }
+        // Mixing an `Int` accumulator with a `Float` sample promotes the result to `Float`
+        // instead of truncating the float, matching tracing-core's first-class `f64` support.
+        *self = match (*self, rhs) {
+            (CounterValue::Int(lhs), CounterValue::Int(rhs)) => CounterValue::Int(lhs + rhs),
+            (CounterValue::Int(lhs), CounterValue::Float(rhs)) => {
+                CounterValue::Float(lhs as f64 + rhs)
+            }
+            (CounterValue::Float(lhs), CounterValue::Int(rhs)) => {
+                CounterValue::Float(lhs + rhs as f64)
+            }
+            (CounterValue::Float(lhs), CounterValue::Float(rhs)) => CounterValue::Float(lhs + rhs),
+        };
     }
 }
 
@@ -152,60 +272,291 @@ impl AddAssign<u64> for CounterValue {
     }
 }
 
+impl CounterValue {
+    pub(crate) fn as_f64(self) -> f64 {
+        match self {
+            CounterValue::Int(value) => value as f64,
+            CounterValue::Float(value) => value,
+        }
+    }
+}
+
+/// Streaming (single-pass) distribution statistics for a counter series: running count, mean and
+/// Welford's `M2` (sum of squared deviations from the running mean), updated via the
+/// numerically-stable formula so `variance` never needs to revisit earlier samples, nor suffers
+/// the catastrophic cancellation a naive sum-of-squares accumulator hits for series with a large
+/// mean and small spread. This is tracked alongside (not instead of) the running total a series'
+/// samples add up to, since a hot span's per-invocation cost can be bimodal even when the total
+/// looks unremarkable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CounterStats {
+    n: u64,
+    mean: f64,
+    m2: f64,
+    min: f64,
+    max: f64,
+}
+
+impl CounterStats {
+    /// Starts a new series from its first sample.
+    pub fn new(value: CounterValue) -> Self {
+        let x = value.as_f64();
+        Self {
+            n: 1,
+            mean: x,
+            m2: 0.0,
+            min: x,
+            max: x,
+        }
+    }
+
+    /// Folds another sample into the running statistics via Welford's online algorithm.
+    pub fn record(&mut self, value: CounterValue) {
+        let x = value.as_f64();
+        self.n += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.n as f64;
+        let delta2 = x - self.mean;
+        self.m2 += delta * delta2;
+        self.min = self.min.min(x);
+        self.max = self.max.max(x);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.n
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.n == 0 {
+            0.0
+        } else {
+            self.mean
+        }
+    }
+
+    /// Population variance. `0.0` for fewer than two samples, where variance is undefined.
+    pub fn variance(&self) -> f64 {
+        if self.n < 2 {
+            0.0
+        } else {
+            self.m2 / self.n as f64
+        }
+    }
+
+    pub fn stddev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+}
+
+impl std::fmt::Display for CounterStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "n={} mean={:.2} stddev={:.2} min={:.2} max={:.2}",
+            self.n,
+            self.mean(),
+            self.stddev(),
+            self.min,
+            self.max
+        )
+    }
+}
+
+const COUNTER_VALUE_FIELD: &str = "value";
+const IS_COUNTER_FIELD: &str = "counter";
+const IS_INCREMENTAL_FIELD: &str = "incremental";
+const PERFETTO_CATEGORY_FIELD: &str = "perfetto_category";
+const UNIT_FIELD: &str = "unit";
+const PERFETTO_TRACK_ID_FIELD: &str = "perfetto_track_id";
+
+/// Configures which field names `CounterVisitor` treats as reserved, and whether it reports
+/// a single counter value or one series per numeric field.
+#[derive(Debug, Clone)]
+pub struct CounterVisitorConfig {
+    /// Name of the field holding the counter's value, used when `multi_series` is false.
+    pub value_field: &'static str,
+    /// Name of the boolean field marking an event as a counter.
+    pub is_counter_field: &'static str,
+    /// Name of the boolean field marking a counter as incremental.
+    pub is_incremental_field: &'static str,
+    /// Name of the field holding the perfetto category.
+    pub category_field: &'static str,
+    /// Name of the field holding the counter's unit.
+    pub unit_field: &'static str,
+    /// Name of the field holding a perfetto track id, so the counter plots on a dedicated, named
+    /// counter track (see `perfetto_sys::create_counter_event`) instead of the default one.
+    pub track_id_field: &'static str,
+    /// When `true`, every numeric field other than the reserved control fields above becomes
+    /// its own named counter series in `CounterVisitor::series` (so `counter!(reads = 10, writes
+    /// = 3)` produces two series). When `false` (the default), only `value_field` is recorded,
+    /// into `CounterVisitor::value`.
+    pub multi_series: bool,
+}
+
+impl Default for CounterVisitorConfig {
+    fn default() -> Self {
+        Self {
+            value_field: COUNTER_VALUE_FIELD,
+            is_counter_field: IS_COUNTER_FIELD,
+            is_incremental_field: IS_INCREMENTAL_FIELD,
+            category_field: PERFETTO_CATEGORY_FIELD,
+            unit_field: UNIT_FIELD,
+            track_id_field: PERFETTO_TRACK_ID_FIELD,
+            multi_series: false,
+        }
+    }
+}
+
 // gets the needed data out of an Event by implementing the Visit trait
 #[derive(Default)]
 pub struct CounterVisitor {
     pub value: Option<CounterValue>,
+    /// Populated instead of `value` when `CounterVisitorConfig::multi_series` is enabled: one
+    /// entry per numeric field on the event, named after the field. Structured (`valuable`)
+    /// fields contribute entries keyed by their dotted path, hence the owned `Cow`.
+    pub series: Vec<(Cow<'static, str>, CounterValue)>,
     pub unit: Option<String>,
     pub category: Option<String>,
+    /// The counter's track id, if given via `CounterVisitorConfig::track_id_field`.
+    pub track_id: Option<u64>,
     pub is_counter: bool,
     pub is_incremental: bool,
+    config: CounterVisitorConfig,
 }
 
-const COUNTER_VALUE_FIELD: &str = "value";
-const IS_COUNTER_FIELD: &str = "counter";
-const IS_INCREMENTAL_FIELD: &str = "incremental";
-const PERFETTO_CATEGORY_FIELD: &str = "perfetto_category";
-const UNIT_FIELD: &str = "unit";
+impl CounterVisitor {
+    /// Create a visitor using custom reserved field names and/or the multi-series mode.
+    pub fn new(config: CounterVisitorConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    fn record_numeric(&mut self, name: &'static str, value: CounterValue) {
+        if name == self.config.value_field {
+            self.value.replace(value);
+        } else if self.config.multi_series
+            && name != self.config.is_counter_field
+            && name != self.config.is_incremental_field
+        {
+            self.series.push((Cow::Borrowed(name), value));
+        }
+    }
+}
 
 impl tracing::field::Visit for CounterVisitor {
     fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
-        if field.name() == COUNTER_VALUE_FIELD {
-            self.value.replace(CounterValue::Int(value));
+        let name = field.name();
+        if name == self.config.track_id_field {
+            self.track_id = Some(value);
+        } else {
+            self.record_numeric(name, CounterValue::Int(value));
         }
     }
 
     fn record_i64(&mut self, field: &tracing::field::Field, value: i64) {
-        if field.name() == COUNTER_VALUE_FIELD {
-            self.value.replace(CounterValue::Int(value as _));
+        let name = field.name();
+        if name == self.config.track_id_field {
+            self.track_id = Some(value as _);
+        } else {
+            self.record_numeric(name, CounterValue::Int(value as _));
         }
     }
 
     fn record_f64(&mut self, field: &tracing::field::Field, value: f64) {
-        if field.name() == COUNTER_VALUE_FIELD {
-            self.value.replace(CounterValue::Float(value as _));
-        }
+        self.record_numeric(field.name(), CounterValue::Float(value));
     }
 
     fn record_bool(&mut self, field: &tracing::field::Field, value: bool) {
-        match field.name() {
-            IS_COUNTER_FIELD => self.is_counter = value,
-            IS_INCREMENTAL_FIELD => self.is_incremental = value,
-            _ => {}
+        let name = field.name();
+        if name == self.config.is_counter_field {
+            self.is_counter = value;
+        } else if name == self.config.is_incremental_field {
+            self.is_incremental = value;
         }
     }
 
     fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
-        match field.name() {
-            PERFETTO_CATEGORY_FIELD => {
-                self.category.replace(value.to_string());
-            }
-            UNIT_FIELD => {
-                self.unit.replace(value.to_string());
-            }
-            _ => {}
+        let name = field.name();
+        if name == self.config.category_field {
+            self.category.replace(value.to_string());
+        } else if name == self.config.unit_field {
+            self.unit.replace(value.to_string());
         }
     }
 
     fn record_debug(&mut self, _: &tracing::field::Field, _: &dyn std::fmt::Debug) {}
+
+    /// A structured (`valuable`) payload flattens into zero or more named leaves, not a single
+    /// scalar, so there's no sensible value to fold into `self.value`: it's always routed into
+    /// `series`, regardless of `CounterVisitorConfig::multi_series` (which only decides whether
+    /// multiple *scalar* fields on the same event each become their own series).
+    #[cfg(tracing_unstable)]
+    fn record_value(&mut self, field: &tracing::field::Field, value: valuable::Value<'_>) {
+        let field_name = field.name();
+        flatten_valuable(value, &mut |path, leaf| {
+            if let StructuredLeaf::Numeric(value) = leaf {
+                let key = if path.is_empty() {
+                    Cow::Borrowed(field_name)
+                } else {
+                    Cow::Owned(format!("{field_name}.{path}"))
+                };
+                self.series.push((key, value));
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixed_int_float_addition_promotes_to_float() {
+        let mut total = CounterValue::Int(10);
+        total += CounterValue::Float(0.5);
+        assert_eq!(total, CounterValue::Float(10.5));
+
+        let mut total = CounterValue::Float(0.5);
+        total += CounterValue::Int(10);
+        assert_eq!(total, CounterValue::Float(10.5));
+    }
+
+    #[test]
+    fn same_type_addition_stays_same_type() {
+        let mut total = CounterValue::Int(1);
+        total += CounterValue::Int(2);
+        assert_eq!(total, CounterValue::Int(3));
+
+        let mut total = CounterValue::Float(1.0);
+        total += CounterValue::Float(2.0);
+        assert_eq!(total, CounterValue::Float(3.0));
+    }
+
+    #[test]
+    fn counter_stats_mean_and_variance_match_population_formula() {
+        let mut stats = CounterStats::new(CounterValue::Int(2));
+        for value in [4, 4, 4, 5, 5, 7, 9] {
+            stats.record(CounterValue::Int(value));
+        }
+
+        assert_eq!(stats.count(), 8);
+        assert!((stats.mean() - 5.0).abs() < 1e-9);
+        assert!((stats.variance() - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn counter_stats_variance_is_zero_for_a_single_sample() {
+        let stats = CounterStats::new(CounterValue::Int(42));
+        assert_eq!(stats.variance(), 0.0);
+    }
 }
@@ -1,18 +1,29 @@
 // Copyright 2024-2025 Irreducible Inc.
 
+mod binary_field_visitor;
+mod counter_store;
 mod event_counts;
 mod field_visitor;
 mod guard_wrapper;
 mod log_tree;
+#[cfg(feature = "perfetto_proto")]
+mod perfetto_trace;
 mod span_metadata;
 mod storage_utils;
 
+pub use binary_field_visitor::{read_binary_fields, BinaryFieldValue, BinaryFieldVisitor};
+pub use counter_store::{CounterKey, CounterStore};
 pub(crate) use event_counts::EventCounts;
 #[allow(unused_imports)]
-pub use field_visitor::{CounterValue, CounterVisitor, StoringFieldVisitor, WritingFieldVisitor};
+pub use field_visitor::{
+    CounterStats, CounterValue, CounterVisitor, CounterVisitorConfig, StoringFieldVisitor,
+    WritingFieldVisitor,
+};
 #[allow(unused_imports)]
 pub(super) use guard_wrapper::GuardWrapper;
 pub use log_tree::LogTree;
+#[cfg(feature = "perfetto_proto")]
+pub use perfetto_trace::PerfettoTraceWriter;
 pub use span_metadata::*;
 #[cfg(feature = "ittapi")]
 pub use storage_utils::insert_to_span_storage;
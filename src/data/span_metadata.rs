@@ -1,5 +1,61 @@
 // Copyright 2024-2025 Irreducible Inc.
 
+/// Per-span state accumulated between `on_new_span`/`on_enter`/`on_exit` for the CSV layer: wall
+/// clock and thread CPU timestamps taken in `on_enter`, the child `rayon_ns` rollup tracked via
+/// the `cpu_time` event, and the span's own recorded fields. See `layers::csv`.
+pub struct CsvMetadata {
+    pub(crate) start_time: Option<u64>,
+    pub(crate) cpu_start_time: Option<nix::sys::time::TimeSpec>,
+    pub(crate) rayon_ns: u64,
+    pub(crate) fields: linear_map::LinearMap<&'static str, String>,
+    /// Hardware counter snapshot taken in `on_enter`, used to compute this span's own delta in
+    /// `on_exit`. `None` until the first `on_enter`.
+    #[cfg(feature = "perf_counters")]
+    pub(crate) counters_at_enter: Option<HwCounters>,
+}
+
+/// A snapshot (or, once subtracted, a delta) of the hardware counters the CSV layer attributes
+/// per span. Lives here rather than in `layers::csv` so `CsvMetadata` can hold one without a
+/// dependency from `data` back onto the layer that reads it.
+#[cfg(feature = "perf_counters")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HwCounters {
+    pub instructions: u64,
+    pub cycles: u64,
+}
+
+#[cfg(feature = "perf_counters")]
+impl std::ops::Sub for HwCounters {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            instructions: self.instructions.saturating_sub(rhs.instructions),
+            cycles: self.cycles.saturating_sub(rhs.cycles),
+        }
+    }
+}
+
+#[cfg(feature = "perf_counters")]
+impl std::ops::AddAssign for HwCounters {
+    fn add_assign(&mut self, rhs: Self) {
+        self.instructions += rhs.instructions;
+        self.cycles += rhs.cycles;
+    }
+}
+
+/// Per-span state for the tree-aggregation summary layer: wall clock and thread CPU timestamps
+/// taken in `on_enter`, and the inclusive-time rollup from already-finished direct children,
+/// tracked the same way the CSV layer rolls up `rayon_ns` onto a parent in `on_exit`. Subtracting
+/// `child_inclusive_ns` from a span's own inclusive time gives its "own time" for the summary
+/// table. See `layers::summary`.
+#[derive(Default)]
+pub struct SummaryMetadata {
+    pub(crate) start_time: Option<u64>,
+    pub(crate) cpu_start_time: Option<nix::sys::time::TimeSpec>,
+    pub(crate) child_inclusive_ns: u64,
+}
+
 #[cfg(feature = "perfetto")]
 pub struct PerfettoMetadata {
     event_data: Option<perfetto_sys::EventData>,
@@ -27,3 +83,18 @@ impl PerfettoMetadata {
         self.trace_guard = None;
     }
 }
+
+/// Per-span state for the OpenTelemetry bridge: the live OTel [`Context`](opentelemetry::Context)
+/// (span + its parent linkage) created for this `tracing` span in `on_new_span`, kept around so
+/// `on_record` can add attributes to the same span and `on_close` can end it.
+#[cfg(feature = "opentelemetry")]
+pub struct OtelMetadata {
+    pub cx: opentelemetry::Context,
+}
+
+#[cfg(feature = "opentelemetry")]
+impl OtelMetadata {
+    pub fn new(cx: opentelemetry::Context) -> Self {
+        Self { cx }
+    }
+}
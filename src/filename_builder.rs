@@ -6,10 +6,13 @@
 //! with various components like timestamp, git information, system details, and custom metadata.
 //! It provides flexible file naming with environment variable overrides.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration as ChronoDuration, Local, TimeZone, Timelike};
+use fs2::FileExt;
 use thiserror::Error;
 
-use crate::filename_utils::{get_formatted_time, get_git_info, sanitize_filename};
+use crate::filename_utils::{find_repo_root, get_formatted_time, get_git_info};
 
 /// Errors that can occur when building trace filenames.
 #[derive(Debug, Clone, Error)]
@@ -20,6 +23,121 @@ pub enum FilenameBuilderError {
     InvalidConfig(String),
 }
 
+/// Rotation policy for a continuously-running trace file, modeled on rolling file appenders:
+/// once the active trace crosses the configured boundary, it's finalized and a fresh one is
+/// started with an up-to-date timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    /// Roll over at the top of every hour.
+    Hourly,
+    /// Roll over at midnight every day.
+    Daily,
+    /// Roll over once the active file reaches this many bytes.
+    SizeBytes(u64),
+    /// Never roll over; the initial file is used for the lifetime of the program.
+    Never,
+}
+
+impl Rotation {
+    /// Returns the next calendar-aligned boundary strictly after `from`, or `None` for
+    /// [`SizeBytes`](Self::SizeBytes)/[`Never`](Self::Never), the former checked against the
+    /// file itself instead and the latter never rolling over at all.
+    pub(crate) fn next_boundary(&self, from: DateTime<Local>) -> Option<DateTime<Local>> {
+        match self {
+            Rotation::Hourly => {
+                let this_hour = from.date_naive().and_hms_opt(from.hour(), 0, 0)?;
+                Local.from_local_datetime(&this_hour).single()?.checked_add_signed(ChronoDuration::hours(1))
+            }
+            Rotation::Daily => {
+                let midnight = from.date_naive().and_hms_opt(0, 0, 0)?;
+                Local.from_local_datetime(&midnight).single()?.checked_add_signed(ChronoDuration::days(1))
+            }
+            Rotation::SizeBytes(_) | Rotation::Never => None,
+        }
+    }
+}
+
+/// A trace file whose rotation boundary is tracked over time, produced by
+/// [`TraceFilenameBuilder::build_rotating`].
+///
+/// [`should_rotate`](Self::should_rotate) is a cheap, side-effect-free check that the caller
+/// (typically a background poll loop, or the perfetto layer's event hooks) can call as often as
+/// it likes; [`rotate`](Self::rotate) computes the next path without touching the current file.
+/// Finalizing the active trace and refreshing `.last_perfetto_trace_path`-style bookkeeping are
+/// left to the caller, since both are tied to how the active trace handle gets swapped out.
+#[derive(Debug)]
+pub struct RotatingTrace {
+    builder: TraceFilenameBuilder,
+    rotation: Rotation,
+    current_path: PathBuf,
+    next_boundary: Option<DateTime<Local>>,
+    integrity_header: bool,
+}
+
+impl RotatingTrace {
+    fn new(builder: TraceFilenameBuilder, rotation: Rotation) -> Result<Self, FilenameBuilderError> {
+        let integrity_header = builder.wants_integrity_header();
+        let current_path = builder.clone().timestamp().build()?;
+        let next_boundary = rotation.next_boundary(Local::now());
+        Ok(Self {
+            builder,
+            rotation,
+            current_path,
+            next_boundary,
+            integrity_header,
+        })
+    }
+
+    /// Path of the currently active trace file.
+    pub fn current_path(&self) -> &Path {
+        &self.current_path
+    }
+
+    /// Whether trace files should carry an integrity header once finalized, per
+    /// [`TraceFilenameBuilder::integrity_header`].
+    pub fn wants_integrity_header(&self) -> bool {
+        self.integrity_header
+    }
+
+    /// Whether the active trace has crossed its rotation boundary and should be rolled over.
+    pub fn should_rotate(&self) -> bool {
+        match self.rotation {
+            Rotation::SizeBytes(limit) => std::fs::metadata(&self.current_path)
+                .map(|metadata| metadata.len() >= limit)
+                .unwrap_or(false),
+            Rotation::Hourly | Rotation::Daily => self
+                .next_boundary
+                .is_some_and(|boundary| Local::now() >= boundary),
+            Rotation::Never => false,
+        }
+    }
+
+    /// Computes the next rotated file's path with a freshly-formatted timestamp, advancing the
+    /// tracked boundary. Does not touch the previous file; the caller is responsible for
+    /// finalizing whatever wrote to [`current_path`](Self::current_path) before switching over.
+    ///
+    /// The timestamp alone is second-resolution, so back-to-back rotations (e.g. a tight
+    /// `SizeBytes` boundary) could otherwise collide on the same filename; a numeric disambiguator
+    /// is appended if the freshly-built path would collide with the current one or an existing file.
+    pub fn rotate(&mut self) -> Result<PathBuf, FilenameBuilderError> {
+        let mut next_path = self.builder.clone().timestamp().build()?;
+        let mut attempt = 1u32;
+        while next_path == self.current_path || next_path.exists() {
+            next_path = self
+                .builder
+                .clone()
+                .timestamp()
+                .add("rot", attempt.to_string())
+                .build()?;
+            attempt += 1;
+        }
+
+        self.current_path = next_path.clone();
+        self.next_boundary = self.rotation.next_boundary(Local::now());
+        Ok(next_path)
+    }
+}
+
 /// Builder for constructing perfetto trace filenames with flexible customization.
 ///
 /// # Example
@@ -54,6 +172,70 @@ pub struct TraceFilenameBuilder {
     subdirs: Vec<String>,
     separator: String,
     prefix: Option<String>,
+    suffix: Option<String>,
+    rotation: Option<Rotation>,
+    integrity_header: bool,
+    sanitize_policy: SanitizePolicy,
+}
+
+/// Controls how non-portable characters in filename components (git branch, hostname, custom
+/// fields, ...) are handled before they're joined into the final trace filename. Applied
+/// uniformly to every component in [`build`](TraceFilenameBuilder::build), not just the git
+/// branch.
+#[derive(Debug, Clone)]
+pub struct SanitizePolicy {
+    replacement: char,
+    max_len: Option<usize>,
+    allowed: fn(char) -> bool,
+}
+
+impl SanitizePolicy {
+    /// Create a sanitize policy with the defaults: allow ASCII alphanumerics plus `-`/`_`,
+    /// replacing everything else with `-`, with no length cap.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the character substituted for anything the allowed-character class rejects.
+    pub fn replacement(mut self, replacement: char) -> Self {
+        self.replacement = replacement;
+        self
+    }
+
+    /// Cap each sanitized component to at most `max_len` characters.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    /// Set the predicate deciding which characters pass through unchanged; everything else is
+    /// replaced per [`replacement`](Self::replacement).
+    pub fn allowed_chars(mut self, allowed: fn(char) -> bool) -> Self {
+        self.allowed = allowed;
+        self
+    }
+
+    /// Applies the policy to an already-decoded string component.
+    fn apply(&self, input: &str) -> String {
+        let sanitized: String = input
+            .chars()
+            .map(|c| if (self.allowed)(c) { c } else { self.replacement })
+            .collect();
+        match self.max_len {
+            Some(max_len) => sanitized.chars().take(max_len).collect(),
+            None => sanitized,
+        }
+    }
+}
+
+impl Default for SanitizePolicy {
+    fn default() -> Self {
+        Self {
+            replacement: '-',
+            max_len: None,
+            allowed: |c| c.is_ascii_alphanumeric() || c == '-' || c == '_',
+        }
+    }
 }
 
 impl TraceFilenameBuilder {
@@ -79,19 +261,22 @@ impl TraceFilenameBuilder {
         self
     }
 
-    /// Auto-detect and add all git information (branch, commit, dirty status).
+    /// Auto-detect and add all git information (branch, commit, dirty status). The branch name
+    /// is sanitized per [`sanitize_policy`](Self::sanitize_policy) when [`build`](Self::build)
+    /// is called.
     pub fn git_info(mut self) -> Self {
         if let Some(git_info) = get_git_info() {
-            self.git_branch = Some(sanitize_filename(&git_info.branch));
+            self.git_branch = Some(git_info.branch);
             self.git_commit = Some(git_info.commit_short);
             self.git_dirty = !git_info.is_clean;
         }
         self
     }
 
-    /// Set git branch name (will be sanitized).
+    /// Set git branch name. Sanitized per [`sanitize_policy`](Self::sanitize_policy) when
+    /// [`build`](Self::build) is called.
     pub fn git_branch(mut self, branch: impl Into<String>) -> Self {
-        self.git_branch = Some(sanitize_filename(&branch.into()));
+        self.git_branch = Some(branch.into());
         self
     }
 
@@ -107,18 +292,21 @@ impl TraceFilenameBuilder {
         self
     }
 
-    /// Auto-detect and add hostname.
+    /// Auto-detect and add hostname. Converted via `to_string_lossy` rather than dropped if the
+    /// OS reports a non-UTF8 hostname; sanitized per [`sanitize_policy`](Self::sanitize_policy)
+    /// when [`build`](Self::build) is called.
     pub fn hostname(mut self) -> Self {
-        if let Ok(hostname) = gethostname::gethostname().into_string() {
-            self.hostname = Some(hostname);
-        }
+        self.hostname = Some(gethostname::gethostname().to_string_lossy().into_owned());
         self
     }
 
-    /// Auto-detect and add platform information.
+    /// Auto-detect and add platform information. `PERFETTO_PLATFORM_NAME` is read via
+    /// `var_os`/`to_string_lossy` rather than `var`, so a non-UTF8 value still produces a usable
+    /// component instead of being dropped.
     pub fn platform(mut self) -> Self {
-        let platform = std::env::var("PERFETTO_PLATFORM_NAME")
-            .unwrap_or_else(|_| std::env::consts::ARCH.to_string());
+        let platform = std::env::var_os("PERFETTO_PLATFORM_NAME")
+            .map(|value| value.to_string_lossy().into_owned())
+            .unwrap_or_else(|| std::env::consts::ARCH.to_string());
         self.platform = Some(platform);
         self
     }
@@ -187,6 +375,8 @@ impl TraceFilenameBuilder {
     /// - Adds hostname
     /// - Uses "." as separator
     /// - Sets extension to ".perfetto-trace"
+    /// - Anchors `output_dir` at the git repository root (see
+    ///   [`repo_root`](Self::repo_root)) if `PERFETTO_TRACE_ANCHOR_REPO_ROOT` is set
     ///
     /// All environment variable overrides are still respected when `build()` is called.
     ///
@@ -199,7 +389,11 @@ impl TraceFilenameBuilder {
     /// // Produces: "20250828T103000.main.abc123.dirty.x86_64.hostname.perfetto-trace"
     /// ```
     pub fn from_env() -> Self {
-        Self::new().timestamp().git_info().platform().hostname()
+        let mut builder = Self::new().timestamp().git_info().platform().hostname();
+        if std::env::var("PERFETTO_TRACE_ANCHOR_REPO_ROOT").is_ok() {
+            builder = builder.repo_root();
+        }
+        builder
     }
 
     /// Create a builder with default perfetto trace settings.
@@ -261,23 +455,86 @@ impl TraceFilenameBuilder {
     }
 
     /// Set a prefix that will be prepended to the filename (useful for scripts).
-    pub fn prepend(mut self, prefix: impl Into<String>) -> Self {
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
         self.prefix = Some(prefix.into());
         self
     }
 
+    /// Alias for [`prefix`](Self::prefix), kept for backward compatibility.
+    pub fn prepend(self, prefix: impl Into<String>) -> Self {
+        self.prefix(prefix)
+    }
+
+    /// Set the filename suffix, i.e. the final `.`-delimited component (default: `perfetto-trace`).
+    pub fn suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.suffix = Some(suffix.into());
+        self
+    }
+
+    /// Set a rotation policy for continuously-running traces. Use
+    /// [`build_rotating`](Self::build_rotating) instead of [`build`](Self::build) to get a
+    /// [`RotatingTrace`] that tracks the boundary.
+    pub fn rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = Some(rotation);
+        self
+    }
+
+    /// Marks this builder as wanting a prepended integrity header (magic bytes, payload length,
+    /// and a SHA-256 digest) on its trace files, so corruption/truncation from shipping a trace
+    /// between machines can be caught with `perfetto_sys::verify_trace_file`. Off by default, since
+    /// `trace_processor` and friends expect a bare protobuf stream. Building a path writes no
+    /// header: the flag is carried into [`RotatingTrace::wants_integrity_header`], which the
+    /// perfetto layer's rotation loop checks for each file it rotates away from (not for the file
+    /// still active when the loop stops), and `init_tracing_with_builder` does not read it.
+    pub fn integrity_header(mut self) -> Self {
+        self.integrity_header = true;
+        self
+    }
+
+    /// Whether [`integrity_header`](Self::integrity_header) was requested.
+    pub fn wants_integrity_header(&self) -> bool {
+        self.integrity_header
+    }
+
     /// Set custom separator (default is ".").
     pub fn separator(mut self, separator: impl Into<String>) -> Self {
         self.separator = separator.into();
         self
     }
 
+    /// Set the policy used to sanitize every filename component (git branch, hostname,
+    /// platform, custom fields, ...) — see [`SanitizePolicy`]. Defaults to allowing ASCII
+    /// alphanumerics plus `-`/`_`, replacing everything else with `-`, with no length cap.
+    pub fn sanitize_policy(mut self, policy: SanitizePolicy) -> Self {
+        self.sanitize_policy = policy;
+        self
+    }
+
     /// Set output directory.
     pub fn output_dir(mut self, dir: impl Into<PathBuf>) -> Self {
         self.output_dir = Some(dir.into());
         self
     }
 
+    /// Anchor `output_dir` at the git repository root (see
+    /// [`find_repo_root`](crate::filename_utils::find_repo_root)), so traces land in a stable
+    /// location regardless of the cwd the benchmark happens to be invoked from. Falls back to
+    /// the current `"."` behavior if cwd isn't inside a repository.
+    pub fn repo_root(mut self) -> Self {
+        self.output_dir = Some(find_repo_root().unwrap_or_else(|| PathBuf::from(".")));
+        self
+    }
+
+    /// Like [`repo_root`](Self::repo_root), but joins `rel` under the discovered root — e.g.
+    /// `.output_dir_repo_relative("traces")` collects traces under `<repo>/traces/` no matter
+    /// where the benchmark was invoked from. Falls back to `"."` joined with `rel` if cwd isn't
+    /// inside a repository.
+    pub fn output_dir_repo_relative(mut self, rel: impl AsRef<Path>) -> Self {
+        let root = find_repo_root().unwrap_or_else(|| PathBuf::from("."));
+        self.output_dir = Some(root.join(rel));
+        self
+    }
+
     /// Add a subdirectory level.
     pub fn subdir(mut self, subdir: impl Into<String>) -> Self {
         self.subdirs.push(subdir.into());
@@ -314,6 +571,13 @@ impl TraceFilenameBuilder {
         self.build_impl()
     }
 
+    /// Build the initial trace path and wrap it in a [`RotatingTrace`] that knows when to roll
+    /// over, per the policy set via [`rotation`](Self::rotation) (default: [`Rotation::Daily`]).
+    pub fn build_rotating(self) -> Result<RotatingTrace, FilenameBuilderError> {
+        let rotation = self.rotation.unwrap_or(Rotation::Daily);
+        RotatingTrace::new(self, rotation)
+    }
+
     fn build_impl(self) -> Result<PathBuf, FilenameBuilderError> {
         // Check for complete override first
         if let Ok(path) = std::env::var("PERFETTO_TRACE_FILE_PATH") {
@@ -390,32 +654,24 @@ impl TraceFilenameBuilder {
             parts.push(hostname.clone());
         }
 
+        // Sanitize every component uniformly (git branch, hostname, platform, custom fields, ...)
+        // per the configured policy, then drop any blank components so a stray empty field
+        // doesn't leave a dangling separator.
+        let mut parts: Vec<String> = parts
+            .into_iter()
+            .map(|part| self.sanitize_policy.apply(&part))
+            .collect();
+        parts.retain(|part| !part.is_empty());
+
         // Build filename
+        let suffix = self.suffix.clone().unwrap_or_else(|| "perfetto-trace".to_string());
         let filename = if parts.is_empty() {
-            "trace.perfetto-trace".to_string()
+            format!("trace.{suffix}")
         } else {
-            format!("{}.perfetto-trace", parts.join(&self.separator))
+            format!("{}.{suffix}", parts.join(&self.separator))
         };
 
-        // Determine output directory
-        // If PERFETTO_TRACE_DIR is set, use it as absolute path (ignore subdirs)
-        let full_path = if let Ok(env_dir) = std::env::var("PERFETTO_TRACE_DIR") {
-            // Environment variable overrides everything - use exactly this directory
-            PathBuf::from(env_dir)
-        } else {
-            // Build path with base directory and subdirectories
-            let base_dir = self
-                .output_dir
-                .clone()
-                .unwrap_or_else(|| PathBuf::from("."));
-
-            // Apply subdirectories
-            let mut path = base_dir;
-            for subdir in &self.subdirs {
-                path = path.join(subdir);
-            }
-            path
-        };
+        let full_path = self.resolve_output_dir();
 
         // Create directories if they don't exist
         std::fs::create_dir_all(&full_path).map_err(|e| {
@@ -424,6 +680,105 @@ impl TraceFilenameBuilder {
 
         Ok(full_path.join(filename))
     }
+
+    /// Creates a [`TraceRetention`](crate::trace_retention::TraceRetention) scan rooted at this
+    /// builder's resolved `output_dir`, for pruning old trace files from a long-running
+    /// benchmark loop that calls [`build`](Self::build) repeatedly.
+    pub fn retention(
+        &self,
+        policy: crate::trace_retention::RetentionPolicy,
+    ) -> crate::trace_retention::TraceRetention {
+        crate::trace_retention::TraceRetention::new(self.resolve_output_dir(), policy)
+    }
+
+    /// Determines the output directory, honoring `PERFETTO_TRACE_DIR` and the configured
+    /// `output_dir`/`subdirs`, without touching the filesystem.
+    fn resolve_output_dir(&self) -> PathBuf {
+        // If PERFETTO_TRACE_DIR is set, use it as absolute path (ignore subdirs)
+        if let Ok(env_dir) = std::env::var("PERFETTO_TRACE_DIR") {
+            return PathBuf::from(env_dir);
+        }
+
+        let mut path = self
+            .output_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("."));
+        for subdir in &self.subdirs {
+            path = path.join(subdir);
+        }
+        path
+    }
+
+    /// Reserves a unique run directory for this builder's configured path, taking an advisory
+    /// exclusive lock on a `.lock` file inside it so concurrent benchmark harnesses that land on
+    /// the same directory (e.g. two processes started in the same second against the same
+    /// [`subdir_run_id`](Self::subdir_run_id)) never share an output directory.
+    ///
+    /// If the directory already exists and its lock is held by another process, an incrementing
+    /// numeric suffix (`-1`, `-2`, ...) is appended to the final directory component until one
+    /// can both be created and locked. Directory creation and lock acquisition happen together,
+    /// so the reservation is atomic with respect to other processes racing for the same name.
+    /// The returned [`ReservedRunDir`] holds the lock open for as long as it's alive; drop it
+    /// once the caller only needed the path.
+    pub fn reserve_unique(self) -> Result<ReservedRunDir, FilenameBuilderError> {
+        let base_dir = self.resolve_output_dir();
+
+        let mut attempt = 0u32;
+        loop {
+            let candidate = if attempt == 0 {
+                base_dir.clone()
+            } else {
+                let name = base_dir
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .unwrap_or("run");
+                base_dir.with_file_name(format!("{name}-{attempt}"))
+            };
+
+            std::fs::create_dir_all(&candidate).map_err(|e| {
+                FilenameBuilderError::IoError(format!(
+                    "Failed to create directory {candidate:?}: {e}"
+                ))
+            })?;
+
+            let lock_path = candidate.join(".lock");
+            let lock_file = std::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .open(&lock_path)
+                .map_err(|e| {
+                    FilenameBuilderError::IoError(format!(
+                        "Failed to open lock file {lock_path:?}: {e}"
+                    ))
+                })?;
+
+            if lock_file.try_lock_exclusive().is_ok() {
+                return Ok(ReservedRunDir {
+                    path: candidate,
+                    _lock_file: lock_file,
+                });
+            }
+
+            attempt += 1;
+        }
+    }
+}
+
+/// A uniquely-reserved run directory, returned by [`TraceFilenameBuilder::reserve_unique`].
+/// Holds an advisory exclusive lock on a `.lock` file inside [`path`](Self::path) for as long as
+/// this guard is alive, so another process racing for the same directory name backs off and
+/// tries the next numeric suffix instead of sharing it.
+#[derive(Debug)]
+pub struct ReservedRunDir {
+    path: PathBuf,
+    _lock_file: std::fs::File,
+}
+
+impl ReservedRunDir {
+    /// The reserved, lock-held directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
 }
 
 #[cfg(test)]
@@ -714,4 +1069,69 @@ mod tests {
         assert!(full_path.contains("extended"));
         assert!(full_path.contains("tests"));
     }
+
+    #[test]
+    fn test_custom_suffix() {
+        let path = TraceFilenameBuilder::new()
+            .name("test")
+            .suffix("trace.json")
+            .build()
+            .unwrap();
+
+        let filename = path.file_name().unwrap().to_string_lossy();
+        assert!(filename.ends_with(".trace.json"));
+        assert!(!filename.ends_with(".perfetto-trace"));
+    }
+
+    #[test]
+    fn test_blank_parts_are_skipped() {
+        let path = TraceFilenameBuilder::new()
+            .name("test")
+            .add("empty", "")
+            .build()
+            .unwrap();
+
+        let filename = path.file_name().unwrap().to_string_lossy();
+        // no doubled-up separator from the blank custom field
+        assert!(!filename.contains(".."));
+    }
+
+    #[test]
+    fn test_size_based_rotation_triggers_once_the_file_grows_past_the_limit() {
+        let dir = std::env::temp_dir().join(format!(
+            "trace_filename_builder_rotation_test_{:?}",
+            std::thread::current().id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut rotating = TraceFilenameBuilder::new()
+            .name("rotation_test")
+            .output_dir(&dir)
+            .rotation(Rotation::SizeBytes(4))
+            .build_rotating()
+            .unwrap();
+
+        assert!(!rotating.should_rotate());
+
+        let original_path = rotating.current_path().to_path_buf();
+        std::fs::write(&original_path, b"01234567890").unwrap();
+        assert!(rotating.should_rotate());
+
+        let next_path = rotating.rotate().unwrap();
+        assert_eq!(next_path, rotating.current_path());
+        assert_ne!(next_path, original_path);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_time_based_rotation_does_not_trigger_immediately() {
+        let rotating = TraceFilenameBuilder::new()
+            .name("rotation_test")
+            .rotation(Rotation::Daily)
+            .build_rotating()
+            .unwrap();
+
+        assert!(!rotating.should_rotate());
+    }
 }
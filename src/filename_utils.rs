@@ -1,6 +1,9 @@
 // Copyright 2024-2025 Irreducible Inc.
 
+use std::path::{Path, PathBuf};
+
 use chrono::Local;
+use gix::bstr::ByteSlice;
 
 /// Sample `Local::now()` once and return a pair:
 /// 1) `YYYYMMDDTHHmmss` for filenames (ISO 8601 basic format)
@@ -20,6 +23,30 @@ pub fn sanitize_filename(branch: &str) -> String {
         .collect()
 }
 
+/// Walks upward from the current working directory looking for a `.git` entry (a directory in
+/// a normal checkout, a file pointing elsewhere for a worktree or submodule), returning the
+/// first ancestor that has one. Honors `GIT_DIR`/`GIT_WORK_TREE` if set, matching git's own
+/// precedence, and stops at the filesystem root without finding anything.
+pub fn find_repo_root() -> Option<PathBuf> {
+    if let Ok(work_tree) = std::env::var("GIT_WORK_TREE") {
+        return Some(PathBuf::from(work_tree));
+    }
+    if let Ok(git_dir) = std::env::var("GIT_DIR") {
+        let git_dir = PathBuf::from(git_dir);
+        return Some(git_dir.parent().map(Path::to_path_buf).unwrap_or(git_dir));
+    }
+
+    let mut dir = std::env::current_dir().ok()?;
+    loop {
+        if dir.join(".git").exists() {
+            return Some(dir);
+        }
+        if !dir.pop() {
+            return None;
+        }
+    }
+}
+
 /// Information about the current Git repository HEAD.
 #[derive(Debug)]
 pub struct GitInfo {
@@ -40,6 +67,100 @@ pub struct GitInfo {
     pub is_clean: bool,
 }
 
+/// Returns information about the git repository containing the current working directory,
+/// read directly via `gix` (no `git` binary involved), falling back to the info captured at
+/// build time ([`get_git_info_build_time`]) if the cwd isn't inside a repository — e.g. a
+/// binary copied away from its build tree (Android after deployment, a bare deployment image).
+pub fn get_git_info() -> Option<GitInfo> {
+    get_git_info_runtime().or_else(get_git_info_build_time)
+}
+
+/// Discovers the repository from the current working directory and reads `HEAD` directly
+/// through `gix`. Returns `None` if the cwd isn't inside a git repository.
+fn get_git_info_runtime() -> Option<GitInfo> {
+    let repo = gix::discover(".").ok()?;
+
+    // Detached HEAD has no branch name; fall back to the short commit hash so callers still get
+    // something stable to key a filename on.
+    let branch_name = repo
+        .head_name()
+        .ok()
+        .flatten()
+        .map(|name| name.shorten().to_string());
+
+    // An unborn branch (freshly `git init`'d, no commits yet) has no HEAD commit.
+    let commit = repo.head_commit().ok();
+    let commit_short = commit
+        .as_ref()
+        .map(|commit| commit.id().to_string()[..7].to_string())
+        .unwrap_or_else(|| "nogit".to_string());
+    let commit_message = commit
+        .as_ref()
+        .and_then(|commit| commit.message().ok())
+        .map(|m| String::from_utf8_lossy(m.title.trim()).to_string());
+    let commit_author = commit
+        .as_ref()
+        .and_then(|commit| commit.author().ok())
+        .map(|sig| sig.name.to_string());
+    let commit_time = commit
+        .as_ref()
+        .and_then(|commit| commit.time().ok())
+        .map(|time| format_rfc3339(time.seconds, time.offset / 60));
+
+    let branch = branch_name.unwrap_or_else(|| commit_short.clone());
+
+    // Bare repos have no worktree to diff against; treat as clean rather than erroring.
+    let is_clean = repo.is_dirty().map(|dirty| !dirty).unwrap_or(true);
+
+    Some(GitInfo {
+        branch,
+        commit_short,
+        commit_message,
+        commit_author,
+        commit_time,
+        is_clean,
+    })
+}
+
+/// Formats a Unix timestamp plus a UTC offset (in minutes) as RFC3339, e.g.
+/// `2024-01-02T03:04:05+05:30`. Hand-rolled (using Howard Hinnant's `civil_from_days`
+/// algorithm) to avoid pulling another datetime type into the mix just for a commit timestamp.
+fn format_rfc3339(unix_seconds: i64, offset_minutes: i32) -> String {
+    let local_seconds = unix_seconds + i64::from(offset_minutes) * 60;
+    let days = local_seconds.div_euclid(86400);
+    let secs_of_day = local_seconds.rem_euclid(86400);
+
+    let (year, month, day) = civil_from_days(days);
+    let hour = secs_of_day / 3600;
+    let minute = (secs_of_day % 3600) / 60;
+    let second = secs_of_day % 60;
+
+    let sign = if offset_minutes < 0 { '-' } else { '+' };
+    let offset_minutes = offset_minutes.abs();
+    let offset_hours = offset_minutes / 60;
+    let offset_mins = offset_minutes % 60;
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}{sign}{offset_hours:02}:{offset_mins:02}"
+    )
+}
+
+/// Howard Hinnant's `civil_from_days`: converts a day count since the Unix epoch into a
+/// `(year, month, day)` proleptic-Gregorian civil date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = (z - era * 146097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let y = yoe as i64 + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = if month <= 2 { y + 1 } else { y };
+    (year, month, day)
+}
+
 /// Returns git information captured at compile time.
 ///
 /// This uses git information that was captured during the build process,
@@ -48,7 +169,7 @@ pub struct GitInfo {
 ///
 /// The values are baked into the binary as string literals during compilation,
 /// so no git repository or environment variables are needed at runtime.
-pub fn get_git_info() -> Option<GitInfo> {
+pub fn get_git_info_build_time() -> Option<GitInfo> {
     // These env!() macros are resolved at compile time and become string literals
     let branch = env!("BUILD_GIT_BRANCH");
     let commit_short = env!("BUILD_GIT_COMMIT_SHORT");
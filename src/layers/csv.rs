@@ -1,9 +1,10 @@
+use chrono::Local;
 use linear_map::LinearMap;
 use nix::sys::time::TimeValLike;
 use nix::time::{clock_gettime, ClockId};
 use std::fmt;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::time::Instant;
 use tracing::{
@@ -13,6 +14,7 @@ use tracing::{
 
 use crate::data::{with_span_storage_mut, CsvMetadata, StoringFieldVisitor};
 use crate::errors::err_msg;
+use crate::filename_builder::Rotation;
 
 /// CsvLayer (internally called layer::csv)  
 /// This Layer emits logs in CSV format, allowing for fine grained analysis.
@@ -75,6 +77,83 @@ impl Visit for CpuTimeEvent {
     fn record_debug(&mut self, _: &Field, _: &dyn fmt::Debug) {}
 }
 
+/// Per-thread hardware counter reading backing the `instructions`/`cycles` CSV columns, using the
+/// same `perf_event` crate (and the same lazily-initialized, degrade-to-zero-on-failure approach)
+/// as `graph::perf_counters`, the analogous per-span counter reader for the tree layer.
+#[cfg(feature = "perf_counters")]
+mod hw_counters {
+    use std::cell::RefCell;
+
+    use perf_event::{events::Hardware, Builder, Counter, Group};
+
+    use crate::data::HwCounters;
+
+    struct CounterGroup {
+        group: Group,
+        instructions: Counter,
+        cycles: Counter,
+    }
+
+    impl CounterGroup {
+        fn new() -> std::io::Result<Self> {
+            let mut group = Group::new()?;
+            let instructions = Builder::new()
+                .kind(Hardware::INSTRUCTIONS)
+                .group(&mut group)
+                .build()?;
+            let cycles = Builder::new()
+                .kind(Hardware::CPU_CYCLES)
+                .group(&mut group)
+                .build()?;
+            group.enable()?;
+            Ok(Self {
+                group,
+                instructions,
+                cycles,
+            })
+        }
+
+        fn read(&mut self) -> HwCounters {
+            let Ok(counts) = self.group.read() else {
+                return HwCounters::default();
+            };
+            HwCounters {
+                instructions: counts[&self.instructions],
+                cycles: counts[&self.cycles],
+            }
+        }
+    }
+
+    enum State {
+        Uninit,
+        Ready(CounterGroup),
+        Unavailable,
+    }
+
+    thread_local! {
+        static COUNTERS: RefCell<State> = const { RefCell::new(State::Uninit) };
+    }
+
+    /// Reads this thread's hardware counter group, lazily creating it on first use. Returns an
+    /// all-zero snapshot if the counters couldn't be initialized (e.g. missing permissions or
+    /// unsupported hardware), so callers degrade to zero deltas instead of panicking.
+    pub(super) fn read() -> HwCounters {
+        COUNTERS.with(|cell| {
+            let mut state = cell.borrow_mut();
+            if matches!(*state, State::Uninit) {
+                *state = match CounterGroup::new() {
+                    Ok(group) => State::Ready(group),
+                    Err(_) => State::Unavailable,
+                };
+            }
+            match &mut *state {
+                State::Ready(group) => group.read(),
+                State::Uninit | State::Unavailable => HwCounters::default(),
+            }
+        })
+    }
+}
+
 pub struct Layer {
     tx: mpsc::Sender<String>,
     init_time: Instant,
@@ -98,6 +177,148 @@ impl Layer {
             init_time: Instant::now(),
         }
     }
+
+    /// Like [`new`](Self::new), but writes into a rotating sequence of segments instead of one
+    /// unbounded file, modeled on `tracing-appender`'s rolling appender: `<prefix>.<timestamp
+    /// or-index>.<suffix>` files under `dir`, rolled over once `rotation`'s boundary is crossed.
+    /// Each new segment re-emits [`LogRow::header`] at the top, so every rotated file is
+    /// independently parseable by the pandas post-processing script above.
+    pub fn new_rolling(
+        dir: impl AsRef<Path>,
+        prefix: impl Into<String>,
+        suffix: impl Into<String>,
+        rotation: Rotation,
+    ) -> Self {
+        let dir = dir.as_ref().to_path_buf();
+        let prefix = prefix.into();
+        let suffix = suffix.into();
+        std::fs::create_dir_all(&dir).expect("CsvLogger failed to create output directory");
+
+        let (tx, rx) = mpsc::channel::<String>();
+        std::thread::spawn(move || {
+            let mut segment = RollingSegment::open(dir, prefix, suffix, rotation);
+
+            while let Ok(msg) = rx.recv() {
+                if segment.should_rotate() {
+                    segment = segment.rotate();
+                }
+                segment.write(&msg);
+            }
+
+            segment.finish();
+        });
+
+        Self {
+            tx,
+            init_time: Instant::now(),
+        }
+    }
+
+    /// Like [`new_rolling`](Self::new_rolling), but names each segment via the given
+    /// [`TraceFilenameBuilder`](crate::filename_builder::TraceFilenameBuilder) instead of a
+    /// plain `<prefix>.<timestamp>.<suffix>` pattern, so rotated segments can still carry git
+    /// info, iteration numbers, and anything else the builder was configured with — set the
+    /// builder's own [`prefix`](crate::filename_builder::TraceFilenameBuilder::prefix) and
+    /// [`suffix`](crate::filename_builder::TraceFilenameBuilder::suffix) beforehand as needed.
+    #[cfg(feature = "gen_filename")]
+    pub fn new_rolling_with_builder(
+        builder: crate::filename_builder::TraceFilenameBuilder,
+        rotation: Rotation,
+    ) -> Result<Self, crate::filename_builder::FilenameBuilderError> {
+        let mut rotating = builder.rotation(rotation).build_rotating()?;
+        let mut file = std::fs::File::create(rotating.current_path())
+            .expect("CsvLogger failed to open file");
+
+        let (tx, rx) = mpsc::channel::<String>();
+        std::thread::spawn(move || {
+            let _ = file.write(LogRow::header().as_bytes());
+
+            while let Ok(msg) = rx.recv() {
+                if rotating.should_rotate() {
+                    let _ = file.sync_all();
+                    if let Ok(next_path) = rotating.rotate() {
+                        file = std::fs::File::create(&next_path)
+                            .expect("CsvLogger failed to open rotated file");
+                        let _ = file.write(LogRow::header().as_bytes());
+                    }
+                }
+                let _ = file.write(msg.as_bytes());
+            }
+
+            let _ = file.sync_all();
+        });
+
+        Ok(Self {
+            tx,
+            init_time: Instant::now(),
+        })
+    }
+}
+
+/// Tracks the currently open segment for [`Layer::new_rolling`], opening the next
+/// `<prefix>.<timestamp-or-index>.<suffix>` file once `rotation`'s boundary is crossed.
+struct RollingSegment {
+    dir: PathBuf,
+    prefix: String,
+    suffix: String,
+    rotation: Rotation,
+    file: std::fs::File,
+    next_boundary: Option<chrono::DateTime<Local>>,
+}
+
+impl RollingSegment {
+    fn open(dir: PathBuf, prefix: String, suffix: String, rotation: Rotation) -> Self {
+        let path = Self::next_path(&dir, &prefix, &suffix);
+        let mut file = std::fs::File::create(&path).expect("CsvLogger failed to open file");
+        let _ = file.write(LogRow::header().as_bytes());
+
+        Self {
+            next_boundary: rotation.next_boundary(Local::now()),
+            dir,
+            prefix,
+            suffix,
+            rotation,
+            file,
+        }
+    }
+
+    fn next_path(dir: &Path, prefix: &str, suffix: &str) -> PathBuf {
+        let timestamp = Local::now().format("%Y%m%dT%H%M%S").to_string();
+        let mut candidate = dir.join(format!("{prefix}.{timestamp}.{suffix}"));
+        let mut attempt = 1u32;
+        while candidate.exists() {
+            candidate = dir.join(format!("{prefix}.{timestamp}-{attempt}.{suffix}"));
+            attempt += 1;
+        }
+        candidate
+    }
+
+    fn should_rotate(&self) -> bool {
+        match self.rotation {
+            Rotation::SizeBytes(limit) => self
+                .file
+                .metadata()
+                .map(|metadata| metadata.len() >= limit)
+                .unwrap_or(false),
+            Rotation::Hourly | Rotation::Daily => self
+                .next_boundary
+                .is_some_and(|boundary| Local::now() >= boundary),
+            Rotation::Never => false,
+        }
+    }
+
+    fn rotate(mut self) -> Self {
+        let _ = self.file.sync_all();
+        Self::open(self.dir, self.prefix, self.suffix, self.rotation)
+    }
+
+    fn write(&mut self, msg: &str) {
+        let _ = self.file.write(msg.as_bytes());
+    }
+
+    fn finish(&self) {
+        let _ = self.file.sync_all();
+    }
 }
 
 impl<S> tracing_subscriber::Layer<S> for Layer
@@ -108,6 +329,9 @@ where
 {
     // handles log events like debug!
     fn on_event(&self, event: &tracing::Event<'_>, ctx: tracing_subscriber::layer::Context<'_, S>) {
+        #[cfg(feature = "metatrace")]
+        let _timer = crate::metatrace::Timer::start("csv::on_event");
+
         if event.metadata().name() != "cpu_time" {
             return;
         }
@@ -147,10 +371,17 @@ where
             storage.cpu_start_time.replace(
                 clock_gettime(ClockId::CLOCK_THREAD_CPUTIME_ID).expect("failed to get system time"),
             );
+            #[cfg(feature = "perf_counters")]
+            {
+                storage.counters_at_enter = Some(hw_counters::read());
+            }
         });
     }
 
     fn on_exit(&self, id: &span::Id, ctx: tracing_subscriber::layer::Context<'_, S>) {
+        #[cfg(feature = "perf_counters")]
+        let mut own_counters = crate::data::HwCounters::default();
+
         let rayon_ns = if let Some(span) = ctx.span(id) {
             if let Some(storage) = span.extensions_mut().get_mut::<CsvMetadata>() {
                 let end_cpu_time = clock_gettime(ClockId::CLOCK_THREAD_CPUTIME_ID)
@@ -168,11 +399,21 @@ where
                 let mut cpu_ns = if cpu_diff > 0 { cpu_diff as u64 } else { 0_u64 };
                 cpu_ns += storage.rayon_ns;
 
+                #[cfg(feature = "perf_counters")]
+                {
+                    let current = hw_counters::read();
+                    own_counters = current - storage.counters_at_enter.unwrap_or(current);
+                }
+
                 let log_row = LogRow {
                     span_name: span.name().into(),
                     start_ns: start_time,
                     elapsed_ns: end_time - start_time,
                     cpu_ns,
+                    #[cfg(feature = "perf_counters")]
+                    instructions: own_counters.instructions,
+                    #[cfg(feature = "perf_counters")]
+                    cycles: own_counters.cycles,
                     fields,
                 };
                 let msg = format!("{log_row}\n");
@@ -210,6 +451,8 @@ where
             cpu_start_time: None,
             rayon_ns: 0,
             fields: LinearMap::new(),
+            #[cfg(feature = "perf_counters")]
+            counters_at_enter: None,
         };
 
         // warning: the library user must use #[instrument(skip_all)] or else too much data will be logged
@@ -227,12 +470,28 @@ struct LogRow {
     start_ns: u64,
     elapsed_ns: u64,
     cpu_ns: u64,
+    /// Instructions retired between this span's `on_enter` and `on_exit` on the thread that ran
+    /// it (see `hw_counters`), which covers nested spans on that same thread; unlike `rayon_ns`,
+    /// counts from child spans on other threads are not rolled up into it. Only present with the
+    /// `perf_counters` feature, which also adds the matching `instructions` column to
+    /// [`Self::header`].
+    #[cfg(feature = "perf_counters")]
+    instructions: u64,
+    #[cfg(feature = "perf_counters")]
+    cycles: u64,
     fields: LinearMap<&'static str, String>,
 }
 
 impl LogRow {
     fn header<'a>() -> &'a str {
-        "span_name,start_ns,elapsed_ns,cpu_ns,metadata\n"
+        #[cfg(feature = "perf_counters")]
+        {
+            "span_name,start_ns,elapsed_ns,cpu_ns,instructions,cycles,metadata\n"
+        }
+        #[cfg(not(feature = "perf_counters"))]
+        {
+            "span_name,start_ns,elapsed_ns,cpu_ns,metadata\n"
+        }
     }
 }
 
@@ -247,11 +506,29 @@ impl std::fmt::Display for LogRow {
         // needs the outer quote ' marks to be omitted
         // the comma is replaced with a semicolon to ensure pandas doesn't interpret it as a new column
         let fields = format!("{{{}}}", kv.join("; "));
-        write!(
-            f,
-            "{},{},{},{},{}",
-            self.span_name, self.start_ns, self.elapsed_ns, self.cpu_ns, fields
-        )
+
+        #[cfg(feature = "perf_counters")]
+        {
+            write!(
+                f,
+                "{},{},{},{},{},{},{}",
+                self.span_name,
+                self.start_ns,
+                self.elapsed_ns,
+                self.cpu_ns,
+                self.instructions,
+                self.cycles,
+                fields
+            )
+        }
+        #[cfg(not(feature = "perf_counters"))]
+        {
+            write!(
+                f,
+                "{},{},{},{},{}",
+                self.span_name, self.start_ns, self.elapsed_ns, self.cpu_ns, fields
+            )
+        }
     }
 }
 
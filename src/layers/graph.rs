@@ -1,6 +1,7 @@
 // Copyright 2024 Ulvetanna Inc.
 use std::{
     collections::HashMap,
+    path::PathBuf,
     sync::{Arc, Mutex},
     thread::ThreadId,
     time::Instant,
@@ -14,6 +15,91 @@ use crate::{
 use linear_map::LinearMap;
 use tracing::span;
 
+mod chrome_trace;
+#[cfg(feature = "tree_perf_counters")]
+mod perf_counters;
+#[cfg(feature = "tree_stream")]
+mod stream;
+
+/// Formats a byte count with the coarsest binary unit (KiB/MiB/...) that keeps it readable,
+/// e.g. `1.2MiB`, for display in `GraphNode::label`.
+#[cfg(feature = "alloc_counters")]
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes}B")
+    } else {
+        format!("{value:.1}{}", UNITS[unit])
+    }
+}
+
+/// An absolute, depth/duration-based filter for the tree layer, modeled on rust-analyzer's
+/// `RA_PROFILE` syntax: `<names>@<max_depth>><min_ms>`, e.g. `*@3>10` ("render at most 3 levels
+/// deep, and only print a root span at all if it ran longer than 10ms") or `foo|bar@5>0` ("only
+/// show spans named foo or bar, up to 5 levels deep"). Every component is optional; an empty or
+/// `*` name list means "no name filter".
+#[derive(Debug, Clone, Default)]
+struct FilterSpec {
+    /// When set, only spans whose name appears in this list are rendered.
+    names: Option<Vec<&'static str>>,
+    /// When set, children past this depth are collapsed into a single `[...]` node.
+    max_depth: Option<usize>,
+    /// When set, `GraphNode::print` skips the root entirely if it ran for less than this.
+    min_duration: Option<std::time::Duration>,
+}
+
+impl FilterSpec {
+    fn parse(spec: &str) -> Self {
+        let (names, rest) = match spec.split_once('@') {
+            Some((names, rest)) => (Some(names), rest),
+            None => (None, spec),
+        };
+        let (max_depth, min_duration) = match rest.split_once('>') {
+            Some((depth, millis)) => (Some(depth), Some(millis)),
+            None => (Some(rest), None),
+        };
+
+        Self {
+            names: names.and_then(|names| {
+                (!names.is_empty() && names != "*").then(|| {
+                    names
+                        .split('|')
+                        // Leaked once at startup: `&'static str` lets us compare directly against
+                        // span names, which `tracing` guarantees are themselves `&'static str`.
+                        .map(|name| &*Box::leak(name.to_string().into_boxed_str()))
+                        .collect()
+                })
+            }),
+            max_depth: max_depth.and_then(|depth| depth.parse().ok()),
+            min_duration: min_duration
+                .and_then(|millis| millis.parse().ok())
+                .map(std::time::Duration::from_millis),
+        }
+    }
+
+    fn from_env() -> Self {
+        match std::env::var("TREE_LAYER_FILTER") {
+            Ok(spec) => Self::parse(&spec),
+            Err(_) => Self::default(),
+        }
+    }
+
+    fn allows_name(&self, name: &str) -> bool {
+        match &self.names {
+            Some(names) => names.iter().any(|allowed| *allowed == name),
+            None => true,
+        }
+    }
+}
+
 /// Tree layer config.
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -49,6 +135,40 @@ pub struct Config {
     /// Whether to disable color output.
     /// Corresponds to the `NO_COLOR` environment variable.
     pub no_color: bool,
+
+    /// When set, each printed tree is additionally serialized to this path in the Chrome Trace
+    /// Event Format, for loading into `chrome://tracing` or Perfetto's UI.
+    /// Corresponds to the `TREE_LAYER_JSON_OUT` environment variable.
+    pub json_out: Option<PathBuf>,
+
+    /// Absolute depth/duration filter, applied on top of the percentage-based settings above.
+    /// Corresponds to the `TREE_LAYER_FILTER` environment variable; see [`FilterSpec`].
+    filter: FilterSpec,
+
+    /// Whether to measure hardware performance counters (retired instructions, CPU cycles, cache
+    /// misses) for each span and display them alongside the time/percentage.
+    /// Corresponds to the `TREE_LAYER_PERF_COUNTERS` environment variable.
+    #[cfg(feature = "tree_perf_counters")]
+    pub enable_perf_counters: bool,
+
+    /// Whether to measure bytes allocated and allocation count for each span, using the
+    /// thread-local totals maintained by [`crate::CountingAllocator`] (requires the user to have
+    /// installed it as their `#[global_allocator]`).
+    /// Corresponds to the `TREE_LAYER_ALLOC_COUNTERS` environment variable.
+    #[cfg(feature = "alloc_counters")]
+    pub enable_alloc_counters: bool,
+
+    /// Unix-domain socket path to stream live snapshots of still-open spans on, for an external
+    /// viewer in the spirit of `tokio-console` (the tree is otherwise only printed once a root
+    /// span closes). Unset (the default) disables streaming entirely.
+    /// Corresponds to the `TREE_LAYER_STREAM_SOCKET` environment variable.
+    #[cfg(feature = "tree_stream")]
+    pub stream_socket: Option<PathBuf>,
+
+    /// How often, in milliseconds, to push a new snapshot to connected streaming clients.
+    /// Corresponds to the `TREE_LAYER_STREAM_INTERVAL_MS` environment variable.
+    #[cfg(feature = "tree_stream")]
+    pub stream_interval_ms: u64,
 }
 
 impl Config {
@@ -61,8 +181,51 @@ impl Config {
             accumulate_events: get_bool_env_var("TREE_LAYER_ACCUMULATE_EVENTS", true),
             accumulate_spans_count: get_bool_env_var("TREE_LAYER_ACCUMULATE_SPANS_COUNT", false),
             no_color: get_bool_env_var("NO_COLOR", false),
+            json_out: std::env::var("TREE_LAYER_JSON_OUT").ok().map(PathBuf::from),
+            filter: FilterSpec::from_env(),
+            #[cfg(feature = "tree_perf_counters")]
+            enable_perf_counters: get_bool_env_var("TREE_LAYER_PERF_COUNTERS", false),
+            #[cfg(feature = "alloc_counters")]
+            enable_alloc_counters: get_bool_env_var("TREE_LAYER_ALLOC_COUNTERS", false),
+            #[cfg(feature = "tree_stream")]
+            stream_socket: std::env::var("TREE_LAYER_STREAM_SOCKET").ok().map(PathBuf::from),
+            #[cfg(feature = "tree_stream")]
+            stream_interval_ms: get_env_var("TREE_LAYER_STREAM_INTERVAL_MS", 500),
         }
     }
+
+    #[cfg(feature = "tree_perf_counters")]
+    fn perf_counters_enabled(&self) -> bool {
+        self.enable_perf_counters
+    }
+
+    #[cfg(not(feature = "tree_perf_counters"))]
+    fn perf_counters_enabled(&self) -> bool {
+        false
+    }
+
+    #[cfg(feature = "alloc_counters")]
+    fn alloc_counters_enabled(&self) -> bool {
+        self.enable_alloc_counters
+    }
+
+    #[cfg(not(feature = "alloc_counters"))]
+    fn alloc_counters_enabled(&self) -> bool {
+        false
+    }
+
+    /// Socket path and interval to stream live snapshots on, if enabled.
+    #[cfg(feature = "tree_stream")]
+    fn stream_target(&self) -> Option<(&std::path::Path, std::time::Duration)> {
+        self.stream_socket
+            .as_deref()
+            .map(|path| (path, std::time::Duration::from_millis(self.stream_interval_ms)))
+    }
+
+    #[cfg(not(feature = "tree_stream"))]
+    fn stream_target(&self) -> Option<(&std::path::Path, std::time::Duration)> {
+        None
+    }
 }
 
 impl Default for Config {
@@ -71,14 +234,43 @@ impl Default for Config {
     }
 }
 
+/// Per-thread span bookkeeping: each thread builds its own subtree independently, since span ids
+/// are allocated globally but the "currently entered span" is inherently thread-local.
 #[derive(Default)]
-struct State {
+struct ThreadState {
     current_span: Option<span::Id>,
     unfinished_spans: LinearMap<u64, GraphNode>,
+}
+
+#[derive(Default)]
+struct State {
+    threads: HashMap<ThreadId, ThreadState>,
+    /// Roots that finished on a thread other than the main thread and never got stitched under a
+    /// cross-thread parent. Printed as separate subtrees, annotated with their `ThreadId`, once
+    /// the `Guard` drops.
+    worker_roots: Vec<GraphNode>,
     zero_level_events: EventCounts,
 }
 
 impl State {
+    fn thread_mut(&mut self, thread: ThreadId) -> &mut ThreadState {
+        self.threads.entry(thread).or_default()
+    }
+
+    /// Finds an unfinished span by id, regardless of which thread is tracking it. Needed because
+    /// a span's parent (per `span.parent()`) may live on a different thread than the span itself.
+    fn find_unfinished_mut(&mut self, id: u64) -> Option<&mut GraphNode> {
+        self.threads
+            .values_mut()
+            .find_map(|thread| thread.unfinished_spans.get_mut(&id))
+    }
+
+    fn remove_unfinished(&mut self, id: u64) -> Option<GraphNode> {
+        self.threads
+            .values_mut()
+            .find_map(|thread| thread.unfinished_spans.remove(&id))
+    }
+
     fn print_zero_level_events(&mut self) {
         if !self.zero_level_events.is_empty() {
             println!("> {}\n", self.zero_level_events.format().join("\n> "));
@@ -86,10 +278,21 @@ impl State {
             self.zero_level_events.clear();
         }
     }
+
+    fn print_worker_roots(&mut self, config: &Config) {
+        for root in self.worker_roots.drain(..) {
+            root.print(config);
+        }
+    }
 }
 
 pub struct Guard {
     state: Arc<Mutex<State>>,
+    config: Config,
+    /// Background streaming thread, if `Config::stream_socket` was set. Stopped when the guard
+    /// drops.
+    #[cfg(feature = "tree_stream")]
+    _stream: Option<stream::Handle>,
 }
 
 impl Drop for Guard {
@@ -99,12 +302,15 @@ impl Drop for Guard {
         };
 
         state.print_zero_level_events();
+        state.print_worker_roots(&self.config);
     }
 }
 
 /// GraphLayer (internally called layer::graph)
 /// This Layer prints a call graph to stdout. Please note that this layer both prints data about spans and events.
-/// Spans from the threads other than the main thread are not printed. Events from the main thread are attached to the latest main thread span.
+/// Each thread's spans are tracked independently; a span whose parent lives on another thread is
+/// stitched under it, and any other span that roots a non-main thread is printed as its own
+/// subtree, annotated with its `ThreadId`, once the `Guard` drops.
 /// Depending on the `Config::accumulate_events` setting, the layer will either print the events of each span or accumulate the events of the children into the parent.
 ///
 /// example output:
@@ -145,13 +351,20 @@ impl Layer {
             state: state.clone(),
             config: config.clone(),
         };
-        let guard = Guard { state };
 
-        (layer, guard)
-    }
+        #[cfg(feature = "tree_stream")]
+        let stream_handle = config
+            .stream_target()
+            .map(|(socket, interval)| stream::Handle::spawn(state.clone(), socket.to_path_buf(), interval));
+
+        let guard = Guard {
+            state,
+            config,
+            #[cfg(feature = "tree_stream")]
+            _stream: stream_handle,
+        };
 
-    fn is_main_thread(&self) -> bool {
-        self.main_thread == std::thread::current().id()
+        (layer, guard)
     }
 }
 
@@ -167,12 +380,17 @@ where
         id: &span::Id,
         _ctx: tracing_subscriber::layer::Context<'_, S>,
     ) {
-        if !self.is_main_thread() {
-            return;
-        }
+        let thread = std::thread::current().id();
 
         let mut graph_node = GraphNode {
             call_count: 1,
+            thread: (thread != self.main_thread).then_some(thread),
+            #[cfg(feature = "tree_stream")]
+            id: id.into_u64(),
+            #[cfg(feature = "tree_stream")]
+            parent_id: _ctx
+                .span(id)
+                .and_then(|span| span.parent().map(|p| p.id().into_u64())),
             ..Default::default()
         };
         let mut visitor = StoringFieldVisitor(&mut graph_node.metadata);
@@ -182,7 +400,10 @@ where
             return err_msg!("failed to get mutex");
         };
 
-        state.unfinished_spans.insert(id.into_u64(), graph_node);
+        state
+            .thread_mut(thread)
+            .unfinished_spans
+            .insert(id.into_u64(), graph_node);
     }
 
     fn on_record(
@@ -191,51 +412,50 @@ where
         values: &span::Record<'_>,
         _ctx: tracing_subscriber::layer::Context<'_, S>,
     ) {
-        if !self.is_main_thread() {
-            return;
-        }
+        let thread = std::thread::current().id();
 
         let Ok(mut state) = self.state.lock() else {
             return err_msg!("failed to get mutex");
         };
 
-        if let Some(graph_node) = state.unfinished_spans.get_mut(&id.into_u64()) {
+        if let Some(graph_node) = state.thread_mut(thread).unfinished_spans.get_mut(&id.into_u64())
+        {
             let mut visitor = StoringFieldVisitor(&mut graph_node.metadata);
             values.record(&mut visitor);
         }
     }
 
     fn on_enter(&self, id: &span::Id, _ctx: tracing_subscriber::layer::Context<'_, S>) {
-        if !self.is_main_thread() {
-            return;
-        }
+        let thread = std::thread::current().id();
 
         let Ok(mut state) = self.state.lock() else {
             return err_msg!("failed to get mutex");
         };
 
-        state.current_span = Some(id.clone());
-        if let Some(graph_node) = state.unfinished_spans.get_mut(&id.into_u64()) {
+        let thread_state = state.thread_mut(thread);
+        thread_state.current_span = Some(id.clone());
+        if let Some(graph_node) = thread_state.unfinished_spans.get_mut(&id.into_u64()) {
             graph_node.started = Some(Instant::now());
+            graph_node.record_perf_counters_enter(self.config.perf_counters_enabled());
+            graph_node.record_alloc_counters_enter(self.config.alloc_counters_enabled());
         }
 
         state.print_zero_level_events();
     }
 
     fn on_exit(&self, id: &span::Id, ctx: tracing_subscriber::layer::Context<'_, S>) {
-        if !self.is_main_thread() {
-            return;
-        }
-
         let Some(span) = ctx.span(id) else {
             return err_msg!("failed to get span on_exit");
         };
 
+        let thread = std::thread::current().id();
+
         let Ok(mut state) = self.state.lock() else {
             return err_msg!("failed to get mutex");
         };
 
         let mut node = state
+            .thread_mut(thread)
             .unfinished_spans
             .remove(&id.into_u64())
             .unwrap_or_default();
@@ -244,48 +464,46 @@ where
             .map(|started| Instant::elapsed(&started))
             .unwrap_or_default();
         node.name = span.name();
+        node.record_perf_counters_exit();
+        node.record_alloc_counters_exit();
+
+        match span.parent().map(|p| p.id().into_u64()) {
+            // The parent may live on a different thread than the span that just closed (e.g. a
+            // worker thread's root span closing while its parent is still active on the main
+            // thread). Stitch it in wherever that parent is currently tracked.
+            Some(parent_id) => match state.find_unfinished_mut(parent_id) {
+                Some(parent_node) => parent_node.child_nodes.push(node),
+                None => self.finish_root(&mut state, thread, node),
+            },
+            None => self.finish_root(&mut state, thread, node),
+        }
 
-        let parent = match span.parent() {
-            Some(p) => {
-                let Some(parent_node) = state.unfinished_spans.get_mut(&p.id().into_u64()) else {
-                    return err_msg!("failed to get parent node");
-                };
-
-                parent_node.child_nodes.push(node);
-                Some(p.id().clone())
-            }
-            None => {
-                node.print(&self.config);
-
-                None
-            }
-        };
-
-        state.current_span = parent;
+        state.thread_mut(thread).current_span = span.parent().map(|p| p.id().clone());
     }
 
     fn on_event(&self, event: &tracing::Event<'_>, ctx: tracing_subscriber::layer::Context<'_, S>) {
+        #[cfg(feature = "metatrace")]
+        let _timer = crate::metatrace::Timer::start("graph::on_event");
+
         if event.is_root() {
             return;
         }
 
+        let thread = std::thread::current().id();
+
         let Ok(mut state) = self.state.lock() else {
             return err_msg!("failed to get mutex");
         };
 
-        let span_id = if self.is_main_thread() {
-            event
-                .parent()
-                .cloned()
-                .or_else(|| ctx.current_span().id().cloned())
-        } else {
-            // try to attach the event to the latest main thread span
-            state.current_span.clone()
-        };
+        let span_id = event
+            .parent()
+            .cloned()
+            .or_else(|| ctx.current_span().id().cloned())
+            .or_else(|| state.thread_mut(thread).current_span.clone());
 
         match span_id {
             Some(span_id) => {
-                if let Some(graph_node) = state.unfinished_spans.get_mut(&span_id.into_u64()) {
+                if let Some(graph_node) = state.find_unfinished_mut(span_id.into_u64()) {
                     graph_node.events.record(event);
                 }
             }
@@ -296,6 +514,19 @@ where
     }
 }
 
+impl Layer {
+    /// Finishes a span that has no open parent on any thread: prints it immediately if it
+    /// belongs to the main thread (matching the pre-existing behavior), otherwise stashes it as a
+    /// worker-thread root to be printed, annotated with its `ThreadId`, once the `Guard` drops.
+    fn finish_root(&self, state: &mut State, thread: ThreadId, node: GraphNode) {
+        if thread == self.main_thread {
+            node.print(&self.config);
+        } else {
+            state.worker_roots.push(node);
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone)]
 struct GraphNode {
     name: &'static str,
@@ -305,6 +536,38 @@ struct GraphNode {
     events: EventCounts,
     child_nodes: Vec<GraphNode>,
     call_count: usize,
+    /// Set when this span was created on a thread other than the main thread. Only used to
+    /// annotate the label when the node is printed as a standalone worker-thread root; it is not
+    /// cleared once the node is stitched under a cross-thread parent, but only roots are ever
+    /// printed on their own.
+    thread: Option<ThreadId>,
+    /// Hardware counter deltas measured between `on_enter` and `on_exit`. Only populated when the
+    /// `tree_perf_counters` feature and `Config::enable_perf_counters` are both on.
+    #[cfg(feature = "tree_perf_counters")]
+    perf_counters: perf_counters::PerfCounters,
+    /// Counter snapshot taken in `on_enter`, subtracted from the `on_exit` snapshot to get
+    /// `perf_counters`. `None` while the span is open or if counters are disabled.
+    #[cfg(feature = "tree_perf_counters")]
+    perf_counters_at_enter: Option<perf_counters::PerfCounters>,
+    /// Bytes allocated and allocation count measured between `on_enter` and `on_exit`. Only
+    /// populated when the `alloc_counters` feature and `Config::enable_alloc_counters` are both
+    /// on.
+    #[cfg(feature = "alloc_counters")]
+    alloc_counters: crate::alloc::AllocCounters,
+    /// Counter snapshot taken in `on_enter`, subtracted from the `on_exit` snapshot to get
+    /// `alloc_counters`.
+    #[cfg(feature = "alloc_counters")]
+    alloc_counters_at_enter: Option<crate::alloc::AllocCounters>,
+    /// This span's own id, stable for its whole lifetime. Only tracked when the `tree_stream`
+    /// feature is on, to give `graph::stream`'s live snapshots a stable key for client-side
+    /// diffing.
+    #[cfg(feature = "tree_stream")]
+    id: u64,
+    /// This span's parent id at creation time (`None` for roots). Only tracked when
+    /// `tree_stream` is on; used to reconstruct the still-open forest for live snapshots, since
+    /// nesting is otherwise only materialized once a child span closes (see `on_exit`).
+    #[cfg(feature = "tree_stream")]
+    parent_id: Option<u64>,
 }
 
 impl GraphNode {
@@ -319,6 +582,80 @@ impl GraphNode {
         100.0 * self.execution_duration.as_secs_f64() / root_time.as_secs_f64()
     }
 
+    #[cfg(feature = "tree_perf_counters")]
+    fn record_perf_counters_enter(&mut self, enabled: bool) {
+        if enabled {
+            self.perf_counters_at_enter = Some(perf_counters::read());
+        }
+    }
+
+    #[cfg(not(feature = "tree_perf_counters"))]
+    fn record_perf_counters_enter(&mut self, _enabled: bool) {}
+
+    #[cfg(feature = "tree_perf_counters")]
+    fn record_perf_counters_exit(&mut self) {
+        if let Some(at_enter) = self.perf_counters_at_enter.take() {
+            self.perf_counters = perf_counters::read() - at_enter;
+        }
+    }
+
+    #[cfg(not(feature = "tree_perf_counters"))]
+    fn record_perf_counters_exit(&mut self) {}
+
+    #[cfg(feature = "tree_perf_counters")]
+    fn perf_counters_label(&self) -> String {
+        let perf_counters::PerfCounters {
+            instructions,
+            cycles,
+            cache_misses,
+        } = self.perf_counters;
+        if instructions == 0 && cycles == 0 && cache_misses == 0 {
+            String::new()
+        } else {
+            format!(" | {instructions} instr, {cycles} cycles, {cache_misses} cache misses")
+        }
+    }
+
+    #[cfg(not(feature = "tree_perf_counters"))]
+    fn perf_counters_label(&self) -> &'static str {
+        ""
+    }
+
+    #[cfg(feature = "alloc_counters")]
+    fn record_alloc_counters_enter(&mut self, enabled: bool) {
+        if enabled {
+            self.alloc_counters_at_enter = Some(crate::alloc::read());
+        }
+    }
+
+    #[cfg(not(feature = "alloc_counters"))]
+    fn record_alloc_counters_enter(&mut self, _enabled: bool) {}
+
+    #[cfg(feature = "alloc_counters")]
+    fn record_alloc_counters_exit(&mut self) {
+        if let Some(at_enter) = self.alloc_counters_at_enter.take() {
+            self.alloc_counters = crate::alloc::read() - at_enter;
+        }
+    }
+
+    #[cfg(not(feature = "alloc_counters"))]
+    fn record_alloc_counters_exit(&mut self) {}
+
+    #[cfg(feature = "alloc_counters")]
+    fn alloc_counters_label(&self) -> String {
+        let crate::alloc::AllocCounters { bytes, allocs } = self.alloc_counters;
+        if bytes == 0 && allocs == 0 {
+            String::new()
+        } else {
+            format!(" | {} / {allocs} allocs", format_bytes(bytes))
+        }
+    }
+
+    #[cfg(not(feature = "alloc_counters"))]
+    fn alloc_counters_label(&self) -> &'static str {
+        ""
+    }
+
     /// For each node accumulate the events of its children and return the total events.
     fn accumulate_children_events(&mut self, accumulate_spans_count: bool) {
         for child in self.child_nodes.iter_mut() {
@@ -329,6 +666,11 @@ impl GraphNode {
             }
 
             self.events += &child.events;
+            // `alloc_counters` is a delta of a continuous thread-local running total (enter vs.
+            // exit), so a parent's own delta already includes everything its children allocated
+            // during their nested enter/exit window. Re-summing children here would inflate
+            // every ancestor's total by however many descendants it has; `perf_counters` above
+            // uses the same continuous-delta technique and correctly has no equivalent rollup.
         }
     }
 
@@ -339,16 +681,33 @@ impl GraphNode {
     }
 
     fn print(mut self, config: &Config) {
+        if config
+            .filter
+            .min_duration
+            .is_some_and(|min_duration| self.execution_duration < min_duration)
+        {
+            return;
+        }
+
         if config.accumulate_events {
             self.accumulate_children_events(config.accumulate_spans_count);
         }
 
-        let tree = self.render_tree(self.execution_duration, config);
+        if let Some(path) = &config.json_out {
+            if let Err(err) = chrome_trace::write_to_file(&self, std::process::id(), path) {
+                err_msg!("failed to write chrome trace json to {path:?}: {err}");
+            }
+        }
+
+        let tree = self.render_tree(self.execution_duration, config, 0);
         println!("{}", tree);
     }
 
     fn label(&self, root_time: std::time::Duration, config: &Config) -> String {
         let mut info = vec![];
+        if let Some(thread) = self.thread {
+            info.push(format!("(thread {:?})", thread));
+        }
         if self.call_count > 1 {
             info.push(format!("({} calls)", self.call_count))
         } else if !self.metadata.is_empty() {
@@ -363,7 +722,11 @@ impl GraphNode {
         let name = &self.name;
         let execution_time = self.execution_duration;
         let execution_time_percent = self.execution_percentage(root_time);
-        let mut result = format!("{name} [ {execution_time:.2?} | {execution_time_percent:.2}% ]");
+        let mut result = format!(
+            "{name} [ {execution_time:.2?} | {execution_time_percent:.2}%{}{} ]",
+            self.perf_counters_label(),
+            self.alloc_counters_label()
+        );
         if !info.is_empty() {
             result = format!("{result} {}", info.join(" "));
         }
@@ -385,16 +748,23 @@ impl GraphNode {
         }
     }
 
-    fn render_tree(&self, root_time: std::time::Duration, config: &Config) -> LogTree {
+    fn render_tree(&self, root_time: std::time::Duration, config: &Config, depth: usize) -> LogTree {
+        let filtered_children: Vec<GraphNode> = self
+            .child_nodes
+            .iter()
+            .filter(|child| config.filter.allows_name(child.name))
+            .cloned()
+            .collect();
+
         let mut children = vec![];
         let mut aggregated_node: Option<GraphNode> = None;
         let mut name_counter: HashMap<&str, usize> = HashMap::new();
 
-        for (i, child) in self.child_nodes.iter().enumerate() {
+        for (i, child) in filtered_children.iter().enumerate() {
             let name_count = name_counter.entry(child.name).or_insert(0);
             *name_count += 1;
 
-            let next = self.child_nodes.get(i + 1);
+            let next = filtered_children.get(i + 1);
             if next.is_some_and(|next| next.name == child.name) {
                 if child.execution_percentage(root_time) > config.relevant_above_percent {
                     let mut indexed_child = child.clone();
@@ -443,12 +813,35 @@ impl GraphNode {
             children.insert(0, unaccounted);
         }
 
+        if config
+            .filter
+            .max_depth
+            .is_some_and(|max_depth| depth >= max_depth)
+            && !children.is_empty()
+        {
+            let mut collapsed = children
+                .into_iter()
+                .reduce(|acc, child| acc.aggregate(&child))
+                .unwrap_or_else(|| GraphNode::new("[...]"));
+            collapsed.name = "[...]";
+
+            return LogTree {
+                label: self.label(root_time, config),
+                events: self.events.format(),
+                children: vec![LogTree {
+                    label: collapsed.label(root_time, config),
+                    events: vec![],
+                    children: vec![],
+                }],
+            };
+        }
+
         LogTree {
             label: self.label(root_time, config),
             events: self.events.format(),
             children: children
                 .into_iter()
-                .map(|child| child.render_tree(root_time, config))
+                .map(|child| child.render_tree(root_time, config, depth + 1))
                 .collect(),
         }
     }
@@ -457,6 +850,14 @@ impl GraphNode {
         self.execution_duration += other.execution_duration;
         self.call_count += other.call_count;
         self.events += &other.events;
+        #[cfg(feature = "tree_perf_counters")]
+        {
+            self.perf_counters += other.perf_counters;
+        }
+        #[cfg(feature = "alloc_counters")]
+        {
+            self.alloc_counters += other.alloc_counters;
+        }
 
         self
     }
@@ -527,16 +928,79 @@ mod tests {
         drop(_scope3);
 
         let mut state = guard.state.lock().unwrap();
-        let root = state.unfinished_spans.get_mut(&1).unwrap();
+        let root = state.find_unfinished_mut(1).unwrap();
 
         root.accumulate_children_events(true);
 
+        // "child span5" ran on its own thread and has no parent in common with the main thread's
+        // spans, so it is no longer merged into the main thread's root: only the two `proof_size`
+        // events recorded directly on the main thread (1 + 3) show up here.
         assert_eq!(
             *root.events.get("proof_size").unwrap(),
-            CounterValue::Int(10)
+            CounterValue::Int(4)
         );
 
         // remove to avoid an incorrect graph print
-        state.unfinished_spans.remove(&1).unwrap();
+        state.remove_unfinished(1).unwrap();
+
+        // the worker thread's span becomes its own root, carrying its own share of the counter.
+        assert_eq!(state.worker_roots.len(), 1);
+        let worker_root = &state.worker_roots[0];
+        assert_eq!(worker_root.name, "child span5");
+        assert!(worker_root.thread.is_some());
+        assert_eq!(
+            *worker_root.events.get("proof_size").unwrap(),
+            CounterValue::Int(6)
+        );
+    }
+
+    #[test]
+    fn filter_spec_parses_names_depth_and_duration() {
+        let spec = super::FilterSpec::parse("foo|bar@3>10");
+        assert!(spec.allows_name("foo"));
+        assert!(spec.allows_name("bar"));
+        assert!(!spec.allows_name("baz"));
+        assert_eq!(spec.max_depth, Some(3));
+        assert_eq!(spec.min_duration, Some(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn filter_spec_empty_or_star_names_allow_everything() {
+        assert!(super::FilterSpec::parse("").allows_name("anything"));
+        assert!(super::FilterSpec::parse("*").allows_name("anything"));
+        assert!(super::FilterSpec::parse("*@3").allows_name("anything"));
+    }
+
+    #[test]
+    fn filter_spec_missing_depth_or_duration_segments_are_none() {
+        let spec = super::FilterSpec::parse("foo@");
+        assert!(spec.allows_name("foo"));
+        assert_eq!(spec.max_depth, None);
+        assert_eq!(spec.min_duration, None);
+
+        let spec = super::FilterSpec::parse("foo@3");
+        assert_eq!(spec.max_depth, Some(3));
+        assert_eq!(spec.min_duration, None);
+
+        let spec = super::FilterSpec::parse("foo@>10");
+        assert_eq!(spec.max_depth, None);
+        assert_eq!(spec.min_duration, Some(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn filter_spec_non_numeric_depth_or_duration_is_ignored() {
+        let spec = super::FilterSpec::parse("foo@abc>xyz");
+        assert_eq!(spec.max_depth, None);
+        assert_eq!(spec.min_duration, None);
+    }
+
+    #[test]
+    fn filter_spec_repeated_separators_do_not_panic() {
+        let spec = super::FilterSpec::parse("foo@bar@3>10>20");
+        // Only the first `@` and the first `>` split the spec, leaving `bar@3` as the depth segment
+        // and `10>20` as the duration segment; both fail to parse as numbers rather than panicking.
+        assert_eq!(spec.max_depth, None);
+        assert_eq!(spec.min_duration, None);
+        assert!(spec.allows_name("foo"));
     }
 }
@@ -0,0 +1,160 @@
+// Copyright 2024-2025 Irreducible Inc.
+
+//! Serializes a [`GraphNode`](super::GraphNode) tree to the [Chrome Trace Event
+//! Format](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU),
+//! the JSON consumed by `chrome://tracing` and Perfetto's UI. This is a separate view of the
+//! same data the stdout tree prints, driven by [`Config::json_out`](super::Config::json_out).
+
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    io::{self, Write},
+    path::Path,
+    time::Instant,
+};
+
+use super::GraphNode;
+
+/// The "complete" events for every node of one tree (across all of its `perfetto_track_id`/thread
+/// tracks), plus any flow markers collected along the way.
+struct Collector {
+    root_start: Instant,
+    events: String,
+    flows: HashMap<u64, Vec<(i64, i64, u32, u32)>>,
+    first: bool,
+}
+
+impl Collector {
+    fn new(root_start: Instant) -> Self {
+        Self {
+            root_start,
+            events: String::from("{\"traceEvents\":["),
+            flows: HashMap::new(),
+            first: true,
+        }
+    }
+
+    fn push_event(&mut self, json: &str) {
+        if !self.first {
+            self.events.push(',');
+        }
+        self.first = false;
+        self.events.push_str(json);
+    }
+
+    /// Microsecond offset of `instant` from the trace's root start, clamped to zero.
+    fn ts_micros(&self, instant: Instant) -> i64 {
+        instant
+            .checked_duration_since(self.root_start)
+            .unwrap_or_default()
+            .as_micros() as i64
+    }
+
+    fn visit(&mut self, node: &GraphNode, pid: u32) {
+        let start = node.started.unwrap_or(self.root_start);
+        let ts = self.ts_micros(start);
+        let dur = node.execution_duration.as_micros() as i64;
+        let tid = track_id(node, pid);
+
+        let mut args = String::new();
+        for (key, value) in node.metadata.iter() {
+            if !args.is_empty() {
+                args.push(',');
+            }
+            args.push_str(&format!("\"{}\":\"{}\"", escape(key), escape(value)));
+        }
+
+        self.push_event(&format!(
+            "{{\"name\":\"{}\",\"ph\":\"X\",\"ts\":{ts},\"dur\":{dur},\"pid\":{pid},\"tid\":{tid},\"args\":{{{args}}}}}",
+            escape(node.name),
+        ));
+
+        if let Some(flow_id) = node
+            .metadata
+            .get("perfetto_flow_id")
+            .and_then(|v| v.parse().ok())
+        {
+            self.flows
+                .entry(flow_id)
+                .or_default()
+                .push((ts, ts + dur, pid, tid));
+        }
+
+        for child in &node.child_nodes {
+            self.visit(child, pid);
+        }
+    }
+
+    /// Emits a flow event for every recorded `perfetto_flow_id`: the earliest occurrence starts
+    /// the flow (`ph:"s"`), the latest finishes it (`ph:"f"`), and anything in between is a step
+    /// (`ph:"t"`), letting the viewer draw an arrow connecting every span that shares the id.
+    fn finish(mut self) -> String {
+        for (flow_id, mut occurrences) in std::mem::take(&mut self.flows) {
+            occurrences.sort_by_key(|&(ts, ..)| ts);
+            let last = occurrences.len() - 1;
+            for (i, (start, end, pid, tid)) in occurrences.into_iter().enumerate() {
+                let (ph, ts) = match i {
+                    0 => ("s", start),
+                    i if i == last => ("f", end),
+                    _ => ("t", start),
+                };
+                self.push_event(&format!(
+                    "{{\"name\":\"flow\",\"cat\":\"flow\",\"ph\":\"{ph}\",\"id\":{flow_id},\"ts\":{ts},\"pid\":{pid},\"tid\":{tid}}}"
+                ));
+            }
+        }
+
+        self.events.push_str("]}");
+        self.events
+    }
+}
+
+/// `perfetto_track_id` maps directly to the Chrome trace `tid`; nodes without one fall back to a
+/// stable id derived from the thread they ran on, so worker-thread subtrees land on their own
+/// track instead of overlapping the main thread's.
+fn track_id(node: &GraphNode, pid: u32) -> u32 {
+    if let Some(track_id) = node
+        .metadata
+        .get("perfetto_track_id")
+        .and_then(|v| v.parse().ok())
+    {
+        return track_id;
+    }
+
+    match node.thread {
+        Some(thread) => {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            thread.hash(&mut hasher);
+            (hasher.finish() as u32) | 1
+        }
+        None => pid,
+    }
+}
+
+pub(super) fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                out.push_str(&format!("\\u{:04x}", c as u32));
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Renders `root` (and its descendants) as a Chrome Trace Event Format JSON document and writes
+/// it to `path`.
+pub(super) fn write_to_file(root: &GraphNode, pid: u32, path: &Path) -> io::Result<()> {
+    let root_start = root.started.unwrap_or_else(Instant::now);
+    let mut collector = Collector::new(root_start);
+    collector.visit(root, pid);
+
+    std::fs::File::create(path)?.write_all(collector.finish().as_bytes())
+}
@@ -0,0 +1,115 @@
+// Copyright 2024-2025 Irreducible Inc.
+
+//! Per-span hardware performance counter deltas, gated behind the `tree_perf_counters` feature
+//! and `Config::enable_perf_counters`. Mirrors the approach of rust-analyzer's `stop_watch.rs`: a
+//! thread-local [`perf_event::Group`] is read once when a span is entered and again when it
+//! exits, and the difference is attributed to that span. Since it's a delta, it naturally
+//! includes whatever the span's children also measured, matching the existing time semantics.
+
+use std::cell::RefCell;
+
+use perf_event::{events::Hardware, Builder, Counter, Group};
+
+/// A snapshot (or, once subtracted, a delta) of the hardware counters tracked per span.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(super) struct PerfCounters {
+    pub instructions: u64,
+    pub cycles: u64,
+    pub cache_misses: u64,
+}
+
+impl std::ops::Sub for PerfCounters {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            instructions: self.instructions.saturating_sub(rhs.instructions),
+            cycles: self.cycles.saturating_sub(rhs.cycles),
+            cache_misses: self.cache_misses.saturating_sub(rhs.cache_misses),
+        }
+    }
+}
+
+impl std::ops::AddAssign for PerfCounters {
+    fn add_assign(&mut self, rhs: Self) {
+        self.instructions += rhs.instructions;
+        self.cycles += rhs.cycles;
+        self.cache_misses += rhs.cache_misses;
+    }
+}
+
+struct CounterGroup {
+    group: Group,
+    instructions: Counter,
+    cycles: Counter,
+    cache_misses: Counter,
+}
+
+impl CounterGroup {
+    fn new() -> std::io::Result<Self> {
+        let mut group = Group::new()?;
+        let instructions = Builder::new()
+            .kind(Hardware::INSTRUCTIONS)
+            .group(&mut group)
+            .build()?;
+        let cycles = Builder::new()
+            .kind(Hardware::CPU_CYCLES)
+            .group(&mut group)
+            .build()?;
+        let cache_misses = Builder::new()
+            .kind(Hardware::CACHE_MISSES)
+            .group(&mut group)
+            .build()?;
+        group.enable()?;
+
+        Ok(Self {
+            group,
+            instructions,
+            cycles,
+            cache_misses,
+        })
+    }
+
+    fn read(&mut self) -> PerfCounters {
+        let Ok(counts) = self.group.read() else {
+            return PerfCounters::default();
+        };
+
+        PerfCounters {
+            instructions: counts[&self.instructions],
+            cycles: counts[&self.cycles],
+            cache_misses: counts[&self.cache_misses],
+        }
+    }
+}
+
+enum State {
+    Uninit,
+    Ready(CounterGroup),
+    /// Initialization failed once (e.g. missing permissions); don't keep retrying on every span.
+    Unavailable,
+}
+
+thread_local! {
+    static COUNTERS: RefCell<State> = const { RefCell::new(State::Uninit) };
+}
+
+/// Reads the current value of this thread's hardware counter group, lazily creating it on first
+/// use. Returns an all-zero snapshot if the counters couldn't be initialized, so callers degrade
+/// to zero deltas instead of panicking.
+pub(super) fn read() -> PerfCounters {
+    COUNTERS.with(|cell| {
+        let mut state = cell.borrow_mut();
+        if matches!(*state, State::Uninit) {
+            *state = match CounterGroup::new() {
+                Ok(group) => State::Ready(group),
+                Err(_) => State::Unavailable,
+            };
+        }
+
+        match &mut *state {
+            State::Ready(group) => group.read(),
+            State::Uninit | State::Unavailable => PerfCounters::default(),
+        }
+    })
+}
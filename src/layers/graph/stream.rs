@@ -0,0 +1,183 @@
+// Copyright 2024-2025 Irreducible Inc.
+
+//! Live-streams periodic snapshots of the in-progress span forest over a Unix-domain socket,
+//! for an external viewer in the spirit of the `fabaccess`/`tokio-console` runtime console.
+//! Unlike the stdout tree (which only prints once a root span closes), this serves whatever is
+//! currently open, with provisional durations computed via `Instant::elapsed`, so a long batch
+//! job's hot spots can be watched evolving in real time.
+//!
+//! Each snapshot is written as a length-delimited frame: a 4-byte big-endian length prefix
+//! followed by that many bytes of JSON (the same informal, dependency-free encoding used by
+//! `chrome_trace`). Nodes carry their span id and parent id so a client can diff successive
+//! snapshots instead of re-rendering the whole forest on every frame.
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use super::{chrome_trace, GraphNode, State};
+use crate::errors::err_msg;
+
+/// Owns the background thread started by `Layer::new` when `Config::stream_socket` is set.
+/// Signals the thread to stop and joins it on drop.
+pub(super) struct Handle {
+    shutdown: Arc<AtomicBool>,
+    join: Option<thread::JoinHandle<()>>,
+}
+
+impl Handle {
+    pub(super) fn spawn(state: Arc<Mutex<State>>, socket_path: PathBuf, interval: Duration) -> Self {
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let thread_shutdown = shutdown.clone();
+
+        let join = match thread::Builder::new()
+            .name("tree-stream".to_string())
+            .spawn(move || run(state, socket_path, interval, thread_shutdown))
+        {
+            Ok(join) => Some(join),
+            Err(err) => {
+                err_msg!("failed to spawn tree stream thread: {err}");
+                None
+            }
+        };
+
+        Self { shutdown, join }
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(join) = self.join.take() {
+            let _ = join.join();
+        }
+    }
+}
+
+/// Binds `socket_path` and, until `shutdown` is set, periodically pushes a snapshot of `state`
+/// to every connected client. Polls for new connections and the shutdown flag on the same
+/// interval as it streams, since there's no `select`-style wakeup for a plain blocking
+/// `UnixListener` without pulling in an async runtime.
+fn run(state: Arc<Mutex<State>>, socket_path: PathBuf, interval: Duration, shutdown: Arc<AtomicBool>) {
+    // Remove a socket left behind by a previous, uncleanly-terminated run.
+    let _ = std::fs::remove_file(&socket_path);
+
+    let listener = match UnixListener::bind(&socket_path) {
+        Ok(listener) => listener,
+        Err(err) => return err_msg!("failed to bind tree stream socket {socket_path:?}: {err}"),
+    };
+    if let Err(err) = listener.set_nonblocking(true) {
+        return err_msg!("failed to set tree stream socket non-blocking: {err}");
+    }
+
+    let mut clients: Vec<UnixStream> = vec![];
+    while !shutdown.load(Ordering::Relaxed) {
+        while let Ok((client, _)) = listener.accept() {
+            clients.push(client);
+        }
+
+        if !clients.is_empty() {
+            let snapshot = match state.lock() {
+                Ok(state) => build_snapshot(&state),
+                Err(_) => {
+                    err_msg!("failed to get mutex");
+                    break;
+                }
+            };
+
+            clients.retain_mut(|client| write_frame(client, &snapshot).is_ok());
+        }
+
+        thread::sleep(interval);
+    }
+
+    let _ = std::fs::remove_file(&socket_path);
+}
+
+fn write_frame(stream: &mut UnixStream, payload: &str) -> io::Result<()> {
+    stream.write_all(&(payload.len() as u32).to_be_bytes())?;
+    stream.write_all(payload.as_bytes())
+}
+
+/// Reconstructs the forest of still-open spans and renders it as a JSON document. Open spans
+/// live flat in each thread's `unfinished_spans` map (nesting is normally only materialized in
+/// `child_nodes` once a child closes), so this groups them back into a tree by `parent_id`
+/// instead, and fills in a provisional `execution_duration` via `Instant::elapsed` for any span
+/// that's still running.
+fn build_snapshot(state: &State) -> String {
+    let mut by_id: HashMap<u64, GraphNode> = HashMap::new();
+    for thread_state in state.threads.values() {
+        for (&id, node) in thread_state.unfinished_spans.iter() {
+            let mut node = node.clone();
+            if let Some(started) = node.started {
+                node.execution_duration = started.elapsed();
+            }
+            by_id.insert(id, node);
+        }
+    }
+
+    let mut children_of: HashMap<u64, Vec<u64>> = HashMap::new();
+    let mut roots = vec![];
+    for (&id, node) in &by_id {
+        match node
+            .parent_id
+            .filter(|parent_id| by_id.contains_key(parent_id))
+        {
+            Some(parent_id) => children_of.entry(parent_id).or_default().push(id),
+            None => roots.push(id),
+        }
+    }
+
+    fn attach(id: u64, by_id: &HashMap<u64, GraphNode>, children_of: &HashMap<u64, Vec<u64>>) -> GraphNode {
+        let mut node = by_id[&id].clone();
+        if let Some(child_ids) = children_of.get(&id) {
+            node.child_nodes
+                .extend(child_ids.iter().map(|&id| attach(id, by_id, children_of)));
+        }
+        node
+    }
+
+    let roots: Vec<String> = roots
+        .into_iter()
+        .map(|id| node_to_json(&attach(id, &by_id, &children_of)))
+        .collect();
+
+    format!("{{\"roots\":[{}]}}", roots.join(","))
+}
+
+fn node_to_json(node: &GraphNode) -> String {
+    let mut metadata = String::new();
+    for (key, value) in node.metadata.iter() {
+        if !metadata.is_empty() {
+            metadata.push(',');
+        }
+        metadata.push_str(&format!(
+            "\"{}\":\"{}\"",
+            chrome_trace::escape(key),
+            chrome_trace::escape(value)
+        ));
+    }
+
+    let children: Vec<String> = node.child_nodes.iter().map(node_to_json).collect();
+
+    format!(
+        "{{\"id\":{},\"parent_id\":{},\"name\":\"{}\",\"elapsed_us\":{},\"call_count\":{},\"metadata\":{{{metadata}}},\"children\":[{}]}}",
+        node.id,
+        node.parent_id
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| "null".to_string()),
+        chrome_trace::escape(node.name),
+        node.execution_duration.as_micros(),
+        node.call_count,
+        children.join(","),
+    )
+}
@@ -7,23 +7,66 @@ use tracing_subscriber::filter::EnvFilter;
 use tracing_subscriber::{
     filter::Filtered,
     layer::SubscriberExt,
+    reload,
     util::{SubscriberInitExt, TryInitError},
-    Layer,
+    Layer, Registry,
 };
 
 use crate::{PrintTreeConfig, PrintTreeLayer};
 
-trait WithEnvFilter<S: Subscriber>: Layer<S> + Sized {
-    fn with_env_filter(self) -> Filtered<Self, EnvFilter, S> {
-        let env_level_filter = EnvFilter::builder()
-            .with_default_directive(LevelFilter::DEBUG.into())
-            .from_env_lossy();
+/// A backend's filter that can be swapped out live via [`ReloadHandle::set_filter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Tree,
+    #[cfg(feature = "perfetto")]
+    Perfetto,
+    #[cfg(feature = "ittapi")]
+    IttApi,
+    #[cfg(feature = "tracy")]
+    Tracy,
+    #[cfg(feature = "perf_counters")]
+    PerfCounters,
+    #[cfg(feature = "opentelemetry")]
+    Otel,
+}
+
+/// Parses a directive string using the same grammar as `RUST_LOG`/`EnvFilter` (`target=level`
+/// prefix matching, comma-separated directives), defaulting to `DEBUG` for anything unmatched.
+fn parse_env_filter(directives: &str) -> EnvFilter {
+    EnvFilter::builder()
+        .with_default_directive(LevelFilter::DEBUG.into())
+        .parse_lossy(directives)
+}
 
-        self.with_filter(env_level_filter)
+/// Builds an `EnvFilter` from `var` (e.g. `TRACING_PROFILE_PERFETTO`), falling back to
+/// `RUST_LOG`, and finally to the `DEBUG` default if neither is set.
+fn build_env_filter(var: &str) -> EnvFilter {
+    let directive = std::env::var(var)
+        .or_else(|_| std::env::var("RUST_LOG"))
+        .unwrap_or_else(|_| "debug".to_string());
+
+    parse_env_filter(&directive)
+}
+
+trait WithReloadableEnvFilter<S: Subscriber>: Layer<S> + Sized {
+    /// Like the plain env-filter wiring every backend used to get, except the resulting filter
+    /// is wrapped in [`reload::Layer`], so the returned [`reload::Handle`] can swap in a freshly
+    /// parsed `EnvFilter` later via [`ReloadHandle::set_filter`] without tearing down and
+    /// reinitializing the subscriber. Each backend gets its own independent filter and handle
+    /// this way, same as before.
+    fn with_reloadable_env_filter(
+        self,
+        var: &str,
+    ) -> (
+        Filtered<Self, reload::Layer<EnvFilter, S>, S>,
+        reload::Handle<EnvFilter, S>,
+    ) {
+        let (filter, handle) = reload::Layer::new(build_env_filter(var));
+        (self.with_filter(filter), handle)
     }
 }
 
-impl<S: Subscriber, T: Layer<S>> WithEnvFilter<S> for T {}
+impl<S: Subscriber, T: Layer<S>> WithReloadableEnvFilter<S> for T {}
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -38,8 +81,61 @@ pub enum Error {
     #[cfg(feature = "gen_filename")]
     #[error("failed to initialize filename builder: {0}")]
     FilenameBuilder(#[from] crate::filename_builder::FilenameBuilderError),
+    #[cfg(feature = "opentelemetry")]
+    #[error("failed to initialize OpenTelemetry: {0}")]
+    OpenTelemetry(#[from] crate::layers::otel::OtelError),
+    #[error("failed to reload filter: {0}")]
+    Reload(#[from] reload::Error),
+}
+
+/// A handle for retuning a running process's per-backend log filters live, e.g. from a signal
+/// handler or an admin endpoint, without restarting. Returned alongside the drop guard from
+/// [`init_tracing`]/[`init_tracing_with_builder`]. Cheap to clone and `Send + Sync`, so it can be
+/// stashed in application state and handed out to as many callers as need to adjust verbosity.
+#[derive(Clone)]
+pub struct ReloadHandle {
+    tree: reload::Handle<EnvFilter, Registry>,
+    #[cfg(feature = "perfetto")]
+    perfetto: reload::Handle<EnvFilter, Registry>,
+    #[cfg(feature = "ittapi")]
+    ittapi: reload::Handle<EnvFilter, Registry>,
+    #[cfg(feature = "tracy")]
+    tracy: reload::Handle<EnvFilter, Registry>,
+    #[cfg(feature = "perf_counters")]
+    perf_counters: reload::Handle<EnvFilter, Registry>,
+    #[cfg(feature = "opentelemetry")]
+    otel: reload::Handle<EnvFilter, Registry>,
+}
+
+impl ReloadHandle {
+    /// Parses `directives` using the same grammar as `RUST_LOG`/`EnvFilter` (`target=level`
+    /// prefix matching, comma-separated directives) and swaps it in as `backend`'s filter, live.
+    pub fn set_filter(&self, backend: Backend, directives: &str) -> Result<(), Error> {
+        let new_filter = parse_env_filter(directives);
+
+        match backend {
+            Backend::Tree => self.tree.reload(new_filter)?,
+            #[cfg(feature = "perfetto")]
+            Backend::Perfetto => self.perfetto.reload(new_filter)?,
+            #[cfg(feature = "ittapi")]
+            Backend::IttApi => self.ittapi.reload(new_filter)?,
+            #[cfg(feature = "tracy")]
+            Backend::Tracy => self.tracy.reload(new_filter)?,
+            #[cfg(feature = "perf_counters")]
+            Backend::PerfCounters => self.perf_counters.reload(new_filter)?,
+            #[cfg(feature = "opentelemetry")]
+            Backend::Otel => self.otel.reload(new_filter)?,
+        }
+
+        Ok(())
+    }
 }
 
+/// A backend layer, type-erased to a common `Layer<Registry>` so every backend's
+/// [`reload::Handle`] can share the same subscriber type parameter regardless of which other
+/// backends are compiled in.
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
 // Type aliases to handle conditional compilation cleanly
 #[cfg(feature = "gen_filename")]
 mod filename_support {
@@ -62,75 +158,91 @@ use filename_support::BuilderOption;
 /// - IttApiLayer (if ittapi feature enabled)
 /// - TracyLayer (if tracy feature enabled)
 /// - PrintPerfCountersLayer (if perf_counters feature enabled)
+/// - OtelLayer (if opentelemetry feature enabled)
 ///
 /// The builder parameter allows customization of perfetto trace filenames
 /// when the gen_filename feature is enabled.
-fn init_tracing_internal(_builder: BuilderOption) -> Result<impl Drop, Error> {
+fn init_tracing_internal(_builder: BuilderOption) -> Result<(impl Drop, ReloadHandle), Error> {
+    let mut layers: Vec<BoxedLayer> = Vec::new();
+
     // Create print tree layer
-    let (layer, guard) = PrintTreeLayer::new(PrintTreeConfig::default());
-    let layer = tracing_subscriber::registry().with(layer.with_env_filter());
+    let (tree_layer, guard) = PrintTreeLayer::new(PrintTreeConfig::default());
+    let (tree_layer, tree_handle) = tree_layer.with_reloadable_env_filter("TRACING_PROFILE_TREE");
+    layers.push(Box::new(tree_layer));
 
     // Add perfetto layer if feature is enabled
-    let (layer, guard) = {
-        cfg_if! {
-            if #[cfg(feature = "perfetto")] {
-                let (new_layer, new_guard) = match _builder {
-                    None => {
-                        crate::PerfettoLayer::new_from_env()?
-                    }
-                    Some(builder) => {
-                        crate::PerfettoLayer::new_from_env_with_builder(builder)
-                            .map_err(Error::Perfetto)?
-                    }
-                };
-                (layer.with(new_layer.with_env_filter()), crate::data::GuardWrapper::wrap(guard, new_guard))
-            } else {
-                (layer, guard)
+    #[cfg(feature = "perfetto")]
+    let (guard, perfetto_handle) = {
+        let (new_layer, new_guard) = match _builder {
+            None => crate::PerfettoLayer::new_from_env()?,
+            Some(builder) => {
+                crate::PerfettoLayer::new_from_env_with_builder(builder).map_err(Error::Perfetto)?
             }
-        }
+        };
+        let (new_layer, handle) = new_layer.with_reloadable_env_filter("TRACING_PROFILE_PERFETTO");
+        layers.push(Box::new(new_layer));
+        (crate::data::GuardWrapper::wrap(guard, new_guard), handle)
     };
 
     // Add ITT API layer if feature is enabled
-    let (layer, guard) = {
-        cfg_if! {
-            if #[cfg(feature = "ittapi")] {
-                (layer.with(crate::IttApiLayer::new().with_env_filter()), guard)
-            } else {
-                (layer, guard)
-            }
-        }
+    #[cfg(feature = "ittapi")]
+    let ittapi_handle = {
+        let (new_layer, handle) =
+            crate::IttApiLayer::new().with_reloadable_env_filter("TRACING_PROFILE_ITTAPI");
+        layers.push(Box::new(new_layer));
+        handle
     };
 
     // Add tracy layer if feature is enabled
-    let (layer, guard) = {
-        cfg_if! {
-            if #[cfg(feature = "tracy")] {
-                (layer.with(crate::TracyLayer::default().with_env_filter()), guard)
-            } else {
-                (layer, guard)
-            }
-        }
+    #[cfg(feature = "tracy")]
+    let tracy_handle = {
+        let (new_layer, handle) =
+            crate::TracyLayer::default().with_reloadable_env_filter("TRACING_PROFILE_TRACY");
+        layers.push(Box::new(new_layer));
+        handle
     };
 
     // Add perf counters layer if feature is enabled
-    let (layer, guard) = {
+    #[cfg(feature = "perf_counters")]
+    let perf_counters_handle = {
+        let counters_layer = crate::PrintPerfCountersLayer::new(vec![
+            (
+                "instructions".to_string(),
+                crate::PerfHardwareEvent::INSTRUCTIONS.into(),
+            ),
+            (
+                "cycles".to_string(),
+                crate::PerfHardwareEvent::CPU_CYCLES.into(),
+            ),
+        ])?;
+        let (new_layer, handle) =
+            counters_layer.with_reloadable_env_filter("TRACING_PROFILE_PERF_COUNTERS");
+        layers.push(Box::new(new_layer));
+        handle
+    };
+
+    // Add OpenTelemetry (OTLP) layer if feature is enabled
+    #[cfg(feature = "opentelemetry")]
+    let (guard, otel_handle) = {
+        let (new_layer, new_guard) = crate::OtelLayer::new_from_env()?;
+        let (new_layer, handle) = new_layer.with_reloadable_env_filter("TRACING_PROFILE_OTEL");
+        layers.push(Box::new(new_layer));
+        (crate::data::GuardWrapper::wrap(guard, new_guard), handle)
+    };
+
+    // Wrap the guard so the metatrace observer-effect summary prints when it drops, if enabled.
+    let guard = {
         cfg_if! {
-            if #[cfg(feature = "perf_counters")] {
-                (layer.with(
-                    crate::PrintPerfCountersLayer::new(vec![
-                        ("instructions".to_string(), crate::PerfHardwareEvent::INSTRUCTIONS.into()),
-                        ("cycles".to_string(), crate::PerfHardwareEvent::CPU_CYCLES.into()),
-                    ])?
-                    .with_env_filter(),
-                ), guard)
+            if #[cfg(feature = "metatrace")] {
+                crate::data::GuardWrapper::wrap(guard, crate::metatrace::ReportGuard)
             } else {
-                (layer, guard)
+                guard
             }
         }
     };
 
     // Try to initialize subscriber - OK if already set
-    match layer.try_init() {
+    match tracing_subscriber::registry().with(layers).try_init() {
         Ok(()) => {
             // First initialization succeeded
         }
@@ -141,7 +253,21 @@ fn init_tracing_internal(_builder: BuilderOption) -> Result<impl Drop, Error> {
         }
     }
 
-    Ok(guard)
+    let handle = ReloadHandle {
+        tree: tree_handle,
+        #[cfg(feature = "perfetto")]
+        perfetto: perfetto_handle,
+        #[cfg(feature = "ittapi")]
+        ittapi: ittapi_handle,
+        #[cfg(feature = "tracy")]
+        tracy: tracy_handle,
+        #[cfg(feature = "perf_counters")]
+        perf_counters: perf_counters_handle,
+        #[cfg(feature = "opentelemetry")]
+        otel: otel_handle,
+    };
+
+    Ok((guard, handle))
 }
 
 /// Initialize the tracing with the default values depending on the features enabled and environment variables set.
@@ -151,9 +277,12 @@ fn init_tracing_internal(_builder: BuilderOption) -> Result<impl Drop, Error> {
 /// - `IttApiLayer` (added if feature `ittapi` is enabled)
 /// - `TracyLayer` (added if feature `tracy` is enabled)
 /// - `PrintPerfCountersLayer` (added if feature `perf_counters` is enabled)
+/// - `OtelLayer` (added if feature `opentelemetry` is enabled)
 ///
-/// Returns the guard that should be kept alive for the duration of the program.
-pub fn init_tracing() -> Result<impl Drop, Error> {
+/// Returns a `(guard, handle)` pair: the guard should be kept alive for the duration of the
+/// program, and the handle can be used to retune any backend's filter live via
+/// [`ReloadHandle::set_filter`].
+pub fn init_tracing() -> Result<(impl Drop, ReloadHandle), Error> {
     init_tracing_internal(None)
 }
 
@@ -172,11 +301,11 @@ pub fn init_tracing() -> Result<impl Drop, Error> {
 ///     .timestamp()
 ///     .git_info();
 ///
-/// let _guard = init_tracing_with_builder(builder).unwrap();
+/// let (_guard, _handle) = init_tracing_with_builder(builder).unwrap();
 /// ```
 #[cfg(feature = "gen_filename")]
 pub fn init_tracing_with_builder(
     builder: crate::filename_builder::TraceFilenameBuilder,
-) -> Result<impl Drop, Error> {
+) -> Result<(impl Drop, ReloadHandle), Error> {
     init_tracing_internal(Some(builder))
 }
@@ -1,13 +1,22 @@
 // Copyright 2024-2025 Irreducible Inc.
 
-use ittapi::{Domain, Task};
-use std::{fmt::Write, sync::Once};
+use ittapi::{Counter, Domain, Frame, Task};
+use linear_map::LinearMap;
+use std::{cell::RefCell, collections::HashMap, fmt::Write, sync::Once};
 use tracing::span;
 use tracing_subscriber::{layer, registry::LookupSpan};
 
-use crate::data::{insert_to_span_storage, with_span_storage_mut, WritingFieldVisitor};
+use crate::data::{
+    insert_to_span_storage, with_span_storage_mut, CounterValue, CounterVisitor,
+    StoringFieldVisitor, WritingFieldVisitor,
+};
 use crate::errors::err_msg;
 
+/// Field marking a span as affined to a particular track/thread, same convention as
+/// [`PerfettoLayer`](crate::PerfettoLayer). Spans carrying it are rendered as ITT frames instead
+/// of tasks, since frames are VTune's representation for a recurring, thread-affine phase.
+const PERFETTO_TRACK_ID_FIELD: &str = "perfetto_track_id";
+
 /// A tracing layer that integrates with Intel's Instrumentation and Tracing Technology (ITT) API.
 ///
 /// # Overview
@@ -31,6 +40,14 @@ use crate::errors::err_msg;
 /// Span attributes are included in the task name using the format: `span_name(field1=value1, field2=value2)`.
 /// This helps identify specific instances of spans when analyzing performance data.
 ///
+/// A span carrying a `perfetto_track_id` field (the same thread-affinity convention used by
+/// `PerfettoLayer`) is reported as an ITT `Frame` instead of a `Task`, so long-running, recurring
+/// phases render as frame regions in VTune rather than nested tasks.
+///
+/// `counter=true`/`value=…` events (see `CounterVisitor`) are reported through the ITT Counter
+/// API, keyed by event name, so numeric application metrics show up as their own tracks in the
+/// VTune timeline alongside the task hierarchy.
+///
 /// # Use Cases
 ///
 /// - **Performance Analysis**: Identify which parts of your Rust application consume the most time
@@ -90,38 +107,96 @@ where
             write!(&mut full_name, ")").expect("failed to write");
         }
 
+        let mut fields = LinearMap::new();
+        let mut visitor = StoringFieldVisitor(&mut fields);
+        attrs.record(&mut visitor);
+        let is_frame = fields.get(PERFETTO_TRACK_ID_FIELD).is_some();
+
         insert_to_span_storage(
             id,
             ctx,
             TaskData {
                 name: full_name,
-                task: None,
+                is_frame,
+                region: None,
             },
         );
     }
 
     fn on_enter(&self, id: &span::Id, ctx: layer::Context<'_, S>) {
         with_span_storage_mut::<TaskData, S>(id, ctx, |task_data| {
-            task_data.task = Some(Task::begin(global_domain(), task_data.name.as_str()));
+            task_data.region = Some(if task_data.is_frame {
+                Region::Frame(Frame::begin(global_domain(), task_data.name.as_str()))
+            } else {
+                Region::Task(Task::begin(global_domain(), task_data.name.as_str()))
+            });
         });
     }
 
     fn on_exit(&self, id: &span::Id, ctx: layer::Context<'_, S>) {
-        with_span_storage_mut::<TaskData, S>(id, ctx, |task_data| {
-            if let Some(task) = task_data.task.take() {
-                task.end();
-            } else {
-                err_msg!("task not found for span on exit");
-            }
+        with_span_storage_mut::<TaskData, S>(id, ctx, |task_data| match task_data.region.take() {
+            Some(Region::Task(task)) => task.end(),
+            Some(Region::Frame(frame)) => frame.end(),
+            None => err_msg!("task not found for span on exit"),
         });
     }
 
     fn on_close(&self, _id: span::Id, _ctx: tracing_subscriber::layer::Context<'_, S>) {}
+
+    /// Forwards `counter=true`/`value=…` events (the same convention `PrintTreeLayer` and
+    /// `PerfettoLayer` aggregate) to VTune's Counter API, keyed by event name, so application
+    /// metrics show up as tracks in the timeline alongside the task hierarchy. Events without
+    /// `counter=true` are ignored; unlike the other layers, ITT has no generic "instant event".
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: layer::Context<'_, S>) {
+        #[cfg(feature = "metatrace")]
+        let _timer = crate::metatrace::Timer::start("ittapi::on_event");
+
+        let mut data = CounterVisitor::default();
+        event.record(&mut data);
+
+        if !data.is_counter {
+            return;
+        }
+
+        let value = match data.value {
+            Some(CounterValue::Int(value)) => value as i64,
+            Some(CounterValue::Float(value)) => value as i64,
+            None => {
+                err_msg!("invalid counter event (missing 'value'): {:?}", event);
+                return;
+            }
+        };
+
+        with_named_counter(event.metadata().name(), |counter| counter.set_value(value));
+    }
 }
 
 struct TaskData {
     name: String,
-    task: Option<Task<'static>>,
+    is_frame: bool,
+    region: Option<Region>,
+}
+
+enum Region {
+    Task(Task<'static>),
+    Frame(Frame<'static>),
+}
+
+thread_local! {
+    // ITT counters are cheap handles but not `Sync`; cache one per name per thread so repeated
+    // events for the same counter (e.g. `proof_size` reported every iteration) reuse it rather
+    // than registering a fresh counter with VTune on every event.
+    static COUNTERS: RefCell<HashMap<&'static str, Counter<'static>>> = RefCell::new(HashMap::new());
+}
+
+fn with_named_counter(name: &'static str, f: impl FnOnce(&mut Counter<'static>)) {
+    COUNTERS.with(|counters| {
+        let mut counters = counters.borrow_mut();
+        let counter = counters
+            .entry(name)
+            .or_insert_with(|| Counter::new(global_domain(), name));
+        f(counter);
+    });
 }
 
 /// Returns static domain for ittapi tracing
@@ -3,6 +3,7 @@
 pub mod csv;
 pub mod graph;
 pub mod init_tracing;
+pub mod summary;
 
 #[cfg(feature = "perfetto")]
 pub mod perfetto;
@@ -14,3 +15,6 @@ pub mod ittapi;
 
 #[cfg(feature = "perf_counters")]
 pub mod print_perf_counters;
+
+#[cfg(feature = "opentelemetry")]
+pub mod otel;
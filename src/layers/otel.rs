@@ -0,0 +1,262 @@
+// Copyright 2024-2025 Irreducible Inc.
+
+//! Bridges `tracing` spans to an OTLP collector (Jaeger, Tempo, and friends), in the style of the
+//! `tracing-opentelemetry` crate but self-contained, matching how this crate wraps every other
+//! external client library (`ittapi`, `perfetto-sys`) directly rather than through its own
+//! community adapter. Each `tracing` span starts a matching OTel span when it is created (parented to its
+//! `tracing` parent's OTel context, stored the same way `PerfettoMetadata`/`CsvMetadata` stash
+//! their own per-span state in the span's extensions), maps recorded fields to OTel attributes,
+//! and ends the OTel span when the `tracing` span closes. Export runs through the
+//! `opentelemetry_sdk` batch span processor; since that processor schedules its flush work onto a
+//! Tokio runtime and nothing else in this crate needs one, [`Layer::new_from_env`] spins up a
+//! small dedicated runtime just for the exporter, owned by the returned [`OtelGuard`].
+
+use opentelemetry::{
+    trace::{SpanKind, Status, Tracer, TracerProvider as _},
+    Context, KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use tracing::span;
+
+use crate::data::{with_span_storage_mut, OtelMetadata};
+use crate::errors::err_msg;
+
+const DEFAULT_ENDPOINT: &str = "http://localhost:4317";
+const DEFAULT_SERVICE_NAME: &str = "tracing-profile";
+
+/// Reserved field name for overriding a span's OTel [`SpanKind`] (`client`, `server`, `producer`,
+/// `consumer`; anything else, including unset, maps to `internal`).
+const OTEL_KIND_FIELD: &str = "otel_kind";
+
+/// Errors constructing the OTLP exporter/tracer provider.
+#[derive(Debug, thiserror::Error)]
+pub enum OtelError {
+    #[error("failed to create the OpenTelemetry Tokio runtime: {0}")]
+    Runtime(std::io::Error),
+    #[error("failed to build OTLP span exporter: {0}")]
+    Exporter(String),
+}
+
+pub struct Layer {
+    tracer: opentelemetry_sdk::trace::Tracer,
+}
+
+/// Parses the reserved [`OTEL_KIND_FIELD`] value into a [`SpanKind`], defaulting unset/unknown
+/// values to `internal`.
+fn parse_kind(value: &str) -> SpanKind {
+    match value {
+        "client" => SpanKind::Client,
+        "server" => SpanKind::Server,
+        "producer" => SpanKind::Producer,
+        "consumer" => SpanKind::Consumer,
+        _ => SpanKind::Internal,
+    }
+}
+
+/// Scans a span's attributes for [`OTEL_KIND_FIELD`] without touching the tracer, since the kind
+/// can only be set through the span builder, before the span (and its [`Context`]) exist.
+fn extract_kind(attrs: &span::Attributes<'_>) -> Option<SpanKind> {
+    struct KindOnly(Option<SpanKind>);
+    impl tracing::field::Visit for KindOnly {
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            if field.name() == OTEL_KIND_FIELD {
+                self.0 = Some(parse_kind(value));
+            }
+        }
+        fn record_debug(&mut self, _: &tracing::field::Field, _: &dyn std::fmt::Debug) {}
+    }
+
+    let mut visitor = KindOnly(None);
+    attrs.record(&mut visitor);
+    visitor.0
+}
+
+impl Layer {
+    /// Builds a batch OTLP exporter and tracer provider from the standard OpenTelemetry
+    /// environment variables:
+    /// - `OTEL_EXPORTER_OTLP_ENDPOINT`: collector endpoint. Default: `http://localhost:4317`.
+    /// - `OTEL_SERVICE_NAME`: the `service.name` resource attribute identifying this process in
+    ///   the backend. Default: `tracing-profile`.
+    pub fn new_from_env() -> Result<(Self, OtelGuard), OtelError> {
+        let endpoint = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+            .unwrap_or_else(|_| DEFAULT_ENDPOINT.to_string());
+        let service_name =
+            std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| DEFAULT_SERVICE_NAME.to_string());
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(OtelError::Runtime)?;
+
+        // The batch processor spawns its flush loop via `tokio::spawn` as soon as the provider is
+        // built, so the runtime must already be entered at that point; it doesn't need to stay
+        // entered afterwards; the runtime itself being kept alive (in `OtelGuard`) is enough to
+        // keep driving the spawned task.
+        let _enter = rt.enter();
+
+        let exporter = opentelemetry_otlp::new_exporter()
+            .tonic()
+            .with_endpoint(&endpoint);
+
+        let provider = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(exporter)
+            .with_trace_config(opentelemetry_sdk::trace::Config::default().with_resource(
+                opentelemetry_sdk::Resource::new(vec![KeyValue::new("service.name", service_name)]),
+            ))
+            .install_batch(opentelemetry_sdk::runtime::Tokio)
+            .map_err(|e| OtelError::Exporter(e.to_string()))?;
+
+        let tracer = provider.tracer("tracing-profile");
+        drop(_enter);
+
+        Ok((Self { tracer }, OtelGuard { provider, _rt: rt }))
+    }
+
+    /// Looks up the `tracing` parent span's stored [`Context`], if any, falling back to an empty
+    /// (root) context for a span with no tracked parent.
+    fn parent_context<S>(span: &tracing_subscriber::registry::SpanRef<'_, S>) -> Context
+    where
+        S: for<'lookup> tracing_subscriber::registry::LookupSpan<'lookup>,
+    {
+        span.parent()
+            .and_then(|parent| {
+                parent
+                    .extensions()
+                    .get::<OtelMetadata>()
+                    .map(|m| m.cx.clone())
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Owns the [`opentelemetry_sdk::trace::TracerProvider`] and the dedicated Tokio runtime driving
+/// its batch exporter. Dropping this flushes and shuts down the provider, so no buffered spans
+/// are lost at program exit, mirroring how [`PerfettoGuard`](perfetto_sys::PerfettoGuard)
+/// finalizes its trace on drop.
+pub struct OtelGuard {
+    provider: opentelemetry_sdk::trace::TracerProvider,
+    _rt: tokio::runtime::Runtime,
+}
+
+impl Drop for OtelGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.provider.shutdown() {
+            err_msg!("failed to shut down OpenTelemetry tracer provider: {e}");
+        }
+    }
+}
+
+/// Converts `tracing` field values recorded on a span into OTel attributes on its current span.
+/// [`OTEL_KIND_FIELD`] is skipped here (see [`extract_kind`]): by the time this visitor runs, the
+/// span already exists and its kind can no longer be changed.
+struct AttributeVisitor<'a> {
+    cx: &'a Context,
+}
+
+impl AttributeVisitor<'_> {
+    fn set(&self, key: &'static str, value: impl Into<opentelemetry::Value>) {
+        use opentelemetry::trace::TraceContextExt;
+        self.cx.span().set_attribute(KeyValue::new(key, value));
+    }
+}
+
+impl tracing::field::Visit for AttributeVisitor<'_> {
+    fn record_f64(&mut self, field: &tracing::field::Field, value: f64) {
+        self.set(field.name(), value);
+    }
+
+    fn record_i64(&mut self, field: &tracing::field::Field, value: i64) {
+        self.set(field.name(), value);
+    }
+
+    fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+        self.set(field.name(), value as i64);
+    }
+
+    fn record_bool(&mut self, field: &tracing::field::Field, value: bool) {
+        self.set(field.name(), value);
+    }
+
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        if field.name() == OTEL_KIND_FIELD {
+            return;
+        }
+        self.set(field.name(), value.to_string());
+    }
+
+    fn record_error(
+        &mut self,
+        _field: &tracing::field::Field,
+        value: &(dyn std::error::Error + 'static),
+    ) {
+        use opentelemetry::trace::TraceContextExt;
+        self.cx.span().set_status(Status::error(value.to_string()));
+    }
+
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        if field.name() == OTEL_KIND_FIELD {
+            return;
+        }
+        self.set(field.name(), format!("{value:?}"));
+    }
+}
+
+impl<S> tracing_subscriber::Layer<S> for Layer
+where
+    S: tracing::Subscriber,
+    S: for<'lookup> tracing_subscriber::registry::LookupSpan<'lookup>,
+{
+    fn on_new_span(
+        &self,
+        attrs: &span::Attributes<'_>,
+        id: &span::Id,
+        ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        use opentelemetry::trace::TraceContextExt;
+
+        let Some(span) = ctx.span(id) else {
+            err_msg!("failed to get span on_new_span");
+            return;
+        };
+
+        let parent_cx = Self::parent_context(&span);
+
+        let mut builder = self.tracer.span_builder(span.name().to_string());
+        if let Some(kind) = extract_kind(attrs) {
+            builder = builder.with_kind(kind);
+        }
+        let otel_span = self.tracer.build_with_context(builder, &parent_cx);
+        let cx = parent_cx.with_span(otel_span);
+
+        let mut visitor = AttributeVisitor { cx: &cx };
+        attrs.record(&mut visitor);
+
+        span.extensions_mut().insert(OtelMetadata::new(cx));
+    }
+
+    fn on_record(
+        &self,
+        id: &span::Id,
+        values: &span::Record<'_>,
+        ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        with_span_storage_mut(id, ctx, |storage: &mut OtelMetadata| {
+            let mut visitor = AttributeVisitor { cx: &storage.cx };
+            values.record(&mut visitor);
+        });
+    }
+
+    fn on_close(&self, id: span::Id, ctx: tracing_subscriber::layer::Context<'_, S>) {
+        use opentelemetry::trace::TraceContextExt;
+
+        let Some(span) = ctx.span(&id) else {
+            err_msg!("failed to get span on_close");
+            return;
+        };
+        let removed = span.extensions_mut().remove::<OtelMetadata>();
+        if let Some(storage) = removed {
+            storage.cx.span().end();
+        }
+    }
+}
@@ -1,13 +1,19 @@
 // Copyright 2024-2025 Irreducible Inc.
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
 use perfetto_sys::{create_instant_event, BackendConfig, EventData, PerfettoGuard};
 use tracing::{
     field::{Field, Visit},
     span,
 };
 
-use crate::data::{with_span_storage_mut, CounterValue, CounterVisitor, PerfettoMetadata};
+use crate::data::{CounterValue, CounterVisitor, PerfettoMetadata};
 use crate::errors::err_msg;
+use crate::filename_builder::{Rotation, TraceFilenameBuilder};
 
 use crate::layers::perfetto_utils::{compute_trace_path, emit_run_metadata};
 use crate::utils::{get_formatted_time, get_git_info};
@@ -16,54 +22,201 @@ use crate::utils::{get_formatted_time, get_git_info};
 pub struct PerfettoSettings {
     pub trace_file_path: Option<String>,
     pub buffer_size_kb: Option<usize>,
+    /// Category globs (e.g. `"render_*"`) to enable. Empty means "all categories enabled", matching
+    /// perfetto's own `TrackEventConfig` default.
+    pub enabled_categories: Vec<String>,
+    /// Category globs to disable, taking precedence over `enabled_categories`.
+    pub disabled_categories: Vec<String>,
+    /// Whether to intern repeated `perfetto_category` strings into a process-lifetime pool (see
+    /// [`perfetto_sys::EventData::set_category_interned`]) instead of allocating a fresh
+    /// `CString` for every span/event. Trades a little bookkeeping (one content-keyed pool, never
+    /// freed) for cheaper repeat categories; off by default. This only cuts our own per-call
+    /// allocation — the full category string is still written to the trace on every event, since
+    /// the linked Perfetto SDK (not this crate) owns `track_event`'s wire-level interning.
+    pub intern_categories: bool,
+}
+
+/// Default in-process buffer size, used when `PERFETTO_BUFFER_SIZE_KB` isn't set and as the
+/// fallback buffer for the system backend's rendered `.cfg` (used only when `PERFETTO_CFG_PATH`
+/// isn't set either).
+fn default_buffer_size_kb() -> usize {
+    50 * 1024
+}
+
+/// Splits a comma-separated env var (e.g. `PERFETTO_ENABLED_CATEGORIES=render_*,io_*`) into its
+/// category globs, dropping empty entries so a trailing comma or unset var both yield `Vec::new()`.
+fn parse_category_list_env(var: &str) -> Vec<String> {
+    std::env::var(var)
+        .ok()
+        .map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl PerfettoSettings {
+    /// Reads settings from the same environment variables as [`Layer::new_from_env`], plus
+    /// `PERFETTO_ENABLED_CATEGORIES`/`PERFETTO_DISABLED_CATEGORIES` (comma-separated category
+    /// globs) and `PERFETTO_INTERN_CATEGORIES` (set, to any value, to enable category interning).
+    pub fn from_env() -> Self {
+        Self {
+            trace_file_path: std::env::var("PERFETTO_TRACE_FILE_PATH").ok(),
+            buffer_size_kb: std::env::var("PERFETTO_BUFFER_SIZE_KB")
+                .ok()
+                .and_then(|size| size.parse().ok()),
+            enabled_categories: parse_category_list_env("PERFETTO_ENABLED_CATEGORIES"),
+            disabled_categories: parse_category_list_env("PERFETTO_DISABLED_CATEGORIES"),
+            intern_categories: std::env::var("PERFETTO_INTERN_CATEGORIES").is_ok(),
+        }
+    }
+
+    /// Builds a [`perfetto_sys::TraceConfigBuilder`] for a single in-process-sized ring buffer
+    /// feeding `track_event`, applying `buffer_size_kb` (falling back to
+    /// [`default_buffer_size_kb`]) and any configured category globs.
+    pub fn trace_config_builder(&self) -> perfetto_sys::TraceConfigBuilder {
+        let mut builder = perfetto_sys::TraceConfigBuilder::new();
+        let buffer = builder.add_buffer(
+            self.buffer_size_kb.unwrap_or_else(default_buffer_size_kb),
+            perfetto_sys::FillPolicy::RingBuffer,
+        );
+        builder.map_data_source("track_event", buffer);
+        for category in &self.enabled_categories {
+            builder.enable_category(category);
+        }
+        for category in &self.disabled_categories {
+            builder.disable_category(category);
+        }
+        builder
+    }
 }
 
 const PERFETTO_CATEGORY_FIELD: &str = "perfetto_category";
 const PERFETTO_TRACK_ID_FIELD: &str = "perfetto_track_id";
+const PERFETTO_TRACK_NAME_FIELD: &str = "perfetto_track_name";
 const PERFETTO_FLOW_ID_FIELD: &str = "perfetto_flow_id";
 
-struct SpanVisitor<'a>(&'a mut EventData);
+/// Category used for spans/events that don't set `perfetto_category`.
+const DEFAULT_CATEGORY: &str = "default";
+
+/// Matches `text` against a glob `pattern` where `*` matches any run of characters (no other
+/// metacharacters are supported). Used to match `perfetto_category` values against the globs
+/// configured on [`PerfettoSettings`].
+fn matches_category_glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<usize> = None;
+    let mut match_from = 0;
+
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some(p);
+            match_from = t;
+            p += 1;
+        } else if let Some(star_pos) = star {
+            p = star_pos + 1;
+            match_from += 1;
+            t = match_from;
+        } else {
+            return false;
+        }
+    }
+
+    while p < pattern.len() && pattern[p] == '*' {
+        p += 1;
+    }
+    p == pattern.len()
+}
+
+/// Decides, for a resolved category name, whether it should be emitted at all: an explicit
+/// disable always overrides a matching enable, and an empty `enabled` list means "everything is
+/// enabled", matching perfetto's own `TrackEventConfig` semantics.
+struct CategoryFilter {
+    enabled: Vec<String>,
+    disabled: Vec<String>,
+}
+
+impl CategoryFilter {
+    fn new(settings: &PerfettoSettings) -> Self {
+        Self {
+            enabled: settings.enabled_categories.clone(),
+            disabled: settings.disabled_categories.clone(),
+        }
+    }
+
+    fn is_enabled(&self, category: &str) -> bool {
+        if self.disabled.iter().any(|pattern| matches_category_glob(pattern, category)) {
+            return false;
+        }
+        self.enabled.is_empty()
+            || self.enabled.iter().any(|pattern| matches_category_glob(pattern, category))
+    }
+}
+
+struct SpanVisitor<'a> {
+    event_data: &'a mut EventData,
+    intern_categories: bool,
+}
+
+impl<'a> SpanVisitor<'a> {
+    fn new(event_data: &'a mut EventData, intern_categories: bool) -> Self {
+        Self { event_data, intern_categories }
+    }
+}
 
 impl Visit for SpanVisitor<'_> {
     fn record_str(&mut self, field: &Field, value: &str) {
         match field.name() {
-            PERFETTO_CATEGORY_FIELD => self.0.set_category(value),
+            PERFETTO_CATEGORY_FIELD if self.intern_categories => {
+                self.event_data.set_category_interned(value)
+            }
+            PERFETTO_CATEGORY_FIELD => self.event_data.set_category(value),
+            PERFETTO_TRACK_NAME_FIELD => self.event_data.set_track_name(value),
             field_name => {
-                self.0.add_string_arg(field_name, value);
+                self.event_data.add_string_arg(field_name, value);
             }
         }
     }
 
     fn record_u64(&mut self, field: &Field, value: u64) {
         match field.name() {
-            PERFETTO_TRACK_ID_FIELD => self.0.set_track_id(value),
-            PERFETTO_FLOW_ID_FIELD => self.0.set_flow_id(value),
+            PERFETTO_TRACK_ID_FIELD => self.event_data.set_track_id(value),
+            PERFETTO_FLOW_ID_FIELD => self.event_data.set_flow_id(value),
             field_name => {
-                self.0.add_u64_field(field_name, value);
+                self.event_data.add_u64_field(field_name, value);
             }
         }
     }
 
     fn record_i64(&mut self, field: &Field, value: i64) {
         match field.name() {
-            PERFETTO_TRACK_ID_FIELD => self.0.set_track_id(value as _),
-            PERFETTO_FLOW_ID_FIELD => self.0.set_flow_id(value as _),
+            PERFETTO_TRACK_ID_FIELD => self.event_data.set_track_id(value as _),
+            PERFETTO_FLOW_ID_FIELD => self.event_data.set_flow_id(value as _),
             field_name => {
-                self.0.add_i64_field(field_name, value);
+                self.event_data.add_i64_field(field_name, value);
             }
         }
     }
 
     fn record_f64(&mut self, field: &Field, value: f64) {
-        self.0.add_f64_field(field.name(), value);
+        self.event_data.add_f64_field(field.name(), value);
     }
 
     fn record_bool(&mut self, field: &Field, value: bool) {
-        self.0.add_bool_field(field.name(), value);
+        self.event_data.add_bool_field(field.name(), value);
     }
 
     fn record_debug(&mut self, field: &Field, debug: &dyn std::fmt::Debug) {
-        self.0.add_string_arg(field.name(), &format!("{debug:?}"));
+        self.event_data.add_string_arg(field.name(), &format!("{debug:?}"));
     }
 }
 
@@ -71,8 +224,18 @@ impl Visit for SpanVisitor<'_> {
 ///
 /// The layer support two types of entities:
 /// - spans are converted into perfetto events. The following special fields are supported:
-///   - `perfetto_category`: category of the event. If not specified "default" will be used.
+///   - `perfetto_category`: category of the event. If not specified "default" will be used. A
+///     span/event whose category is disabled (see `PERFETTO_ENABLED_CATEGORIES`/
+///     `PERFETTO_DISABLED_CATEGORIES` on [`Layer::new_from_env`]) is skipped entirely: no span
+///     storage or instant event is created for it.
 ///   - `perfetto_track_id`: track id of the event. See perfetto documentation for more details.
+///     A span carrying this field is rendered as a `TRACE_EVENT_BEGIN`/`END` pair on that async
+///     track rather than on the calling thread's track, so a span that starts on one thread and
+///     finishes on another still renders as a single, continuous region. The first span seen for
+///     a given `perfetto_track_id` names the track (via `perfetto_track_name`, falling back to
+///     the span's own name); later spans sharing the id reuse that name.
+///   - `perfetto_track_name`: display name for `perfetto_track_id`'s track. Optional; only has an
+///     effect the first time a given `perfetto_track_id` is seen.
 ///   - `perfetto_flow_id`: flow id of the event. See perfetto documentation for more details.
 /// - events with `counter` field are converted into perfetto counters. The following special fields are supported:
 ///  - `value`: value of the counter, integer or double. Required.
@@ -86,7 +249,14 @@ impl Visit for SpanVisitor<'_> {
 ///
 /// // guard should be kept alive for the duration of the program
 /// ```
-pub struct Layer {}
+///
+/// For long-running services whose trace would otherwise grow unbounded, use
+/// [`new_with_rotation`](Layer::new_with_rotation) instead: it rolls over to a fresh trace file
+/// whenever the configured [`Rotation`] boundary is crossed.
+pub struct Layer {
+    category_filter: CategoryFilter,
+    intern_categories: bool,
+}
 
 impl Layer {
     /// Create a new layer with the settings from the environment.
@@ -97,6 +267,11 @@ impl Layer {
     /// - `PERFETTO_CFG_PATH`: path to the perfetto config file. If not set, the default one `config/system_profiling.cfg` will be used. Is used only with the system backend.
     /// - `PERFETTO_BUFFER_SIZE_KB`: size of the buffer in kilobytes. Default: 50 * 1024. Is used only with the in-process backend.
     /// - `PERFETTO_PLATFORM_NAME`: custom platform name. Default: architecture of the CPU that is currently in use.
+    /// - `PERFETTO_ENABLED_CATEGORIES`/`PERFETTO_DISABLED_CATEGORIES`: comma-separated category
+    ///   globs to enable/disable. A disabled glob takes precedence over an enabled one. Empty
+    ///   (default) means all categories are enabled.
+    /// - `PERFETTO_INTERN_CATEGORIES`: if set, repeated `perfetto_category` strings are interned
+    ///   into a process-lifetime pool instead of reallocated on every span/event.
     pub fn new_from_env() -> Result<(Self, PerfettoGuard), perfetto_sys::Error> {
         let (timestamp_filename, timestamp_iso) = get_formatted_time();
         let git_info = get_git_info();
@@ -107,20 +282,16 @@ impl Layer {
         // Record the chosen path for external scripts
         std::fs::write(".last_perfetto_trace_path", &output_path_str)?;
 
+        let settings = PerfettoSettings::from_env();
         let backend = match std::env::var("PERFETTO_FUSE") {
             Ok(_) => BackendConfig::System {
                 perfetto_bin_path: std::env::var("PERFETTO_BIN_PATH").ok(),
                 perfetto_cfg_path: std::env::var("PERFETTO_CFG_PATH").ok(),
+                trace_config: settings.trace_config_builder().build(),
+            },
+            Err(_) => BackendConfig::InProcess {
+                trace_config: settings.trace_config_builder().build(),
             },
-            Err(_) => {
-                const DEFAULT_BUFFER_SIZE_KB: usize = 50 * 1024;
-                let buffer_size_kb = match std::env::var("PERFETTO_BUFFER_SIZE_KB") {
-                    Ok(size) => size.parse().unwrap_or(DEFAULT_BUFFER_SIZE_KB),
-                    Err(_) => DEFAULT_BUFFER_SIZE_KB,
-                };
-
-                BackendConfig::InProcess { buffer_size_kb }
-            }
         };
 
         // Start tracing
@@ -128,7 +299,124 @@ impl Layer {
 
         emit_run_metadata(output_path, timestamp_iso, git_info.as_ref());
 
-        Ok((Self {}, guard))
+        let category_filter = CategoryFilter::new(&settings);
+        let intern_categories = settings.intern_categories;
+        Ok((Self { category_filter, intern_categories }, guard))
+    }
+
+    /// Like [`new_from_env`](Self::new_from_env), but rolls over to a fresh trace file whenever
+    /// `rotation`'s boundary is crossed, so a continuously-running service doesn't grow one
+    /// unbounded trace. Checked every `poll_interval` on a dedicated background thread; the
+    /// returned [`RotatingGuard`] owns the active [`PerfettoGuard`] and must be kept alive for the
+    /// duration of the program, same as the guard from `new_from_env`.
+    pub fn new_with_rotation(
+        rotation: Rotation,
+        poll_interval: Duration,
+    ) -> Result<(Self, RotatingGuard), perfetto_sys::Error> {
+        let settings = PerfettoSettings::from_env();
+        let backend = match std::env::var("PERFETTO_FUSE") {
+            Ok(_) => BackendConfig::System {
+                perfetto_bin_path: std::env::var("PERFETTO_BIN_PATH").ok(),
+                perfetto_cfg_path: std::env::var("PERFETTO_CFG_PATH").ok(),
+                trace_config: settings.trace_config_builder().build(),
+            },
+            Err(_) => BackendConfig::InProcess {
+                trace_config: settings.trace_config_builder().build(),
+            },
+        };
+
+        let mut rotating = TraceFilenameBuilder::from_env()
+            .rotation(rotation)
+            .build_rotating()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+
+        let output_path_str = rotating.current_path().to_string_lossy().to_string();
+        std::fs::write(".last_perfetto_trace_path", &output_path_str)?;
+
+        let guard = PerfettoGuard::new(backend.clone(), &output_path_str)?;
+
+        let category_filter = CategoryFilter::new(&settings);
+        let intern_categories = settings.intern_categories;
+        Ok((
+            Self { category_filter, intern_categories },
+            RotatingGuard::spawn(guard, backend, rotating, poll_interval),
+        ))
+    }
+}
+
+/// Owns the active [`PerfettoGuard`] for a [`Layer`] created via
+/// [`Layer::new_with_rotation`](Layer::new_with_rotation), replacing it with a fresh one (on a
+/// fresh trace file) whenever the configured [`Rotation`] boundary is crossed.
+pub struct RotatingGuard {
+    shutdown: Arc<AtomicBool>,
+    join: Option<thread::JoinHandle<()>>,
+}
+
+impl RotatingGuard {
+    fn spawn(
+        mut guard: PerfettoGuard,
+        backend: BackendConfig,
+        mut rotating: crate::filename_builder::RotatingTrace,
+        poll_interval: Duration,
+    ) -> Self {
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let thread_shutdown = shutdown.clone();
+
+        let join = thread::spawn(move || {
+            while !thread_shutdown.load(Ordering::Relaxed) {
+                thread::sleep(poll_interval);
+
+                if !rotating.should_rotate() {
+                    continue;
+                }
+
+                let finalized_path = rotating.current_path().to_path_buf();
+                let next_path = match rotating.rotate() {
+                    Ok(path) => path,
+                    Err(e) => {
+                        err_msg!("failed to compute rotated perfetto trace path: {e}");
+                        continue;
+                    }
+                };
+                let next_path_str = next_path.to_string_lossy().to_string();
+
+                match PerfettoGuard::new(backend.clone(), &next_path_str) {
+                    // Dropping the old `guard` here finalizes (flushes) the trace it was writing.
+                    Ok(new_guard) => {
+                        guard = new_guard;
+                        if rotating.wants_integrity_header() {
+                            if let Err(e) = perfetto_sys::write_integrity_header(&finalized_path) {
+                                err_msg!(
+                                    "failed to write integrity header for {finalized_path:?}: {e}"
+                                );
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        err_msg!("failed to start rotated perfetto trace {next_path:?}: {e}");
+                        continue;
+                    }
+                }
+
+                if let Err(e) = std::fs::write(".last_perfetto_trace_path", &next_path_str) {
+                    err_msg!("failed to update .last_perfetto_trace_path: {e}");
+                }
+            }
+        });
+
+        Self {
+            shutdown,
+            join: Some(join),
+        }
+    }
+}
+
+impl Drop for RotatingGuard {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(join) = self.join.take() {
+            _ = join.join();
+        }
     }
 }
 
@@ -144,13 +432,31 @@ where
         event: &tracing::Event<'_>,
         _ctx: tracing_subscriber::layer::Context<'_, S>,
     ) {
+        #[cfg(feature = "metatrace")]
+        let _timer = crate::metatrace::Timer::start("perfetto::on_event");
+
         let mut data = CounterVisitor::default();
         event.record(&mut data);
 
         // 1) Counter events: record as counters, then exit.
         if data.is_counter {
-            match data.value {
-                Some(CounterValue::Int(value)) => {
+            match (data.track_id, data.value) {
+                // A counter tagged with a track id plots on its own named, continuous-line track
+                // instead of the default unnamed counter.
+                (Some(track_id), Some(value)) => {
+                    #[cfg(feature = "metatrace")]
+                    let _timer = crate::metatrace::Timer::start("perfetto::ffi_counter");
+
+                    perfetto_sys::create_counter_event(
+                        event.metadata().name(),
+                        track_id,
+                        value.as_f64(),
+                    );
+                }
+                (None, Some(CounterValue::Int(value))) => {
+                    #[cfg(feature = "metatrace")]
+                    let _timer = crate::metatrace::Timer::start("perfetto::ffi_counter");
+
                     perfetto_sys::set_counter_u64(
                         event.metadata().name(),
                         data.unit.as_deref(),
@@ -158,7 +464,10 @@ where
                         value,
                     );
                 }
-                Some(CounterValue::Float(value)) => {
+                (None, Some(CounterValue::Float(value))) => {
+                    #[cfg(feature = "metatrace")]
+                    let _timer = crate::metatrace::Timer::start("perfetto::ffi_counter");
+
                     perfetto_sys::set_counter_f64(
                         event.metadata().name(),
                         data.unit.as_deref(),
@@ -166,7 +475,7 @@ where
                         value,
                     );
                 }
-                None => {
+                (_, None) => {
                     err_msg!(
                         "invalid event(missing either 'name' or 'value'): {:?}",
                         event
@@ -180,7 +489,13 @@ where
         // 2) Record the event as an instant event with all key/value fields.
         let name = event.metadata().name();
         let mut event_data = EventData::new(name);
-        event.record(&mut SpanVisitor(&mut event_data));
+        event.record(&mut SpanVisitor::new(&mut event_data, self.intern_categories));
+
+        let category = event_data.category().unwrap_or(DEFAULT_CATEGORY);
+        if !self.category_filter.is_enabled(category) {
+            return;
+        }
+
         create_instant_event(event_data);
     }
 
@@ -202,9 +517,21 @@ where
             Some(span) => {
                 let mut event_data = EventData::new(span.name());
 
-                let mut visitor = SpanVisitor(&mut event_data);
+                let mut visitor = SpanVisitor::new(&mut event_data, self.intern_categories);
                 attrs.record(&mut visitor);
 
+                let category = event_data.category().unwrap_or(DEFAULT_CATEGORY);
+                if !self.category_filter.is_enabled(category) {
+                    // Leave no storage behind: on_enter/on_exit silently no-op for spans without
+                    // it, so a disabled category is skipped at zero ongoing cost.
+                    return;
+                }
+
+                if let Some(track_id) = event_data.track_id() {
+                    let track_name = event_data.track_name().unwrap_or_else(|| span.name());
+                    perfetto_sys::set_track_name(track_id, track_name, event_data.track_parent());
+                }
+
                 let storage = PerfettoMetadata::new(event_data);
                 let mut extensions = span.extensions_mut();
                 extensions.insert(storage);
@@ -216,14 +543,20 @@ where
     }
 
     fn on_enter(&self, id: &span::Id, ctx: tracing_subscriber::layer::Context<'_, S>) {
-        with_span_storage_mut::<PerfettoMetadata, _>(id, ctx, |storage| {
+        let Some(span) = ctx.span(id) else {
+            return err_msg!("failed to get span");
+        };
+        if let Some(storage) = span.extensions_mut().get_mut::<PerfettoMetadata>() {
             storage.start();
-        });
+        }
     }
 
     fn on_exit(&self, id: &span::Id, ctx: tracing_subscriber::layer::Context<'_, S>) {
-        with_span_storage_mut::<PerfettoMetadata, _>(id, ctx, |storage| {
+        let Some(span) = ctx.span(id) else {
+            return err_msg!("failed to get span");
+        };
+        if let Some(storage) = span.extensions_mut().get_mut::<PerfettoMetadata>() {
             storage.end();
-        });
+        }
     }
 }
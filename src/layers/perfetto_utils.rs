@@ -74,5 +74,17 @@ pub(crate) fn emit_run_metadata(
         event_data.add_string_arg("hostname", &host);
     }
 
+    // Build-time metadata (see build.rs): ties a trace back to the exact toolchain, profile,
+    // feature set and dependency graph that produced the binary recording it.
+    event_data.add_string_arg("build_rustc_version", env!("BUILD_RUSTC_VERSION"));
+    event_data.add_string_arg("build_profile", env!("BUILD_PROFILE"));
+    event_data.add_string_arg("build_opt_level", env!("BUILD_OPT_LEVEL"));
+    event_data.add_string_arg("build_debug", env!("BUILD_DEBUG"));
+    event_data.add_string_arg("build_host_triple", env!("BUILD_HOST_TRIPLE"));
+    event_data.add_string_arg("build_features", env!("BUILD_FEATURES"));
+    event_data.add_string_arg("build_dependencies", env!("BUILD_DEPENDENCIES"));
+    event_data.add_bool_field("build_ci", env!("BUILD_CI") == "true");
+    event_data.add_string_arg("build_ci_provider", env!("BUILD_CI_PROVIDER"));
+
     create_instant_event(event_data);
 }
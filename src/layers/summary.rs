@@ -0,0 +1,292 @@
+// Copyright 2024-2025 Irreducible Inc.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::Instant;
+
+use nix::sys::time::TimeValLike;
+use nix::time::{clock_gettime, ClockId};
+use tracing::span;
+
+use crate::data::{with_span_storage_mut, SummaryMetadata};
+use crate::errors::err_msg;
+
+/// SummaryLayer (internally called layer::summary)
+/// In the style of `tracing-forest`, this layer buffers nothing about a span's subtree shape and
+/// does no printing on the hot path: each finished span's durations are sent over an `mpsc`
+/// channel to a single dedicated aggregation thread, which folds them into a
+/// `HashMap<&'static str, Aggregate>` keyed by span name. On [`Guard`] drop, the aggregation
+/// thread is asked to emit a table sorted by total inclusive time descending, giving a
+/// flamegraph-free "where did the time go" view without post-processing a per-span log.
+///
+/// "Own time" for a span is its inclusive time minus the summed inclusive time of its direct
+/// children, tracked the same way the CSV layer (`layers::csv`) rolls up `rayon_ns` onto a
+/// parent in `on_exit`.
+///
+/// example output:
+/// ```bash
+/// cargo test summary1 -- --nocapture
+///
+/// span_name,calls,own_time,inclusive_time,cpu_time
+/// parent span,1,20.11ms,40.25ms,35.12µs
+/// child span,1,20.14ms,20.14ms,18.40µs
+/// ```
+pub struct Config {
+    /// Path to additionally write the summary table as CSV once the [`Guard`] drops.
+    /// Corresponds to the `SUMMARY_LAYER_CSV_OUT` environment variable.
+    pub csv_out: Option<PathBuf>,
+
+    /// Path to additionally write the summary table as JSON once the [`Guard`] drops.
+    /// Corresponds to the `SUMMARY_LAYER_JSON_OUT` environment variable.
+    pub json_out: Option<PathBuf>,
+}
+
+impl Config {
+    fn from_env() -> Self {
+        Self {
+            csv_out: std::env::var("SUMMARY_LAYER_CSV_OUT").ok().map(PathBuf::from),
+            json_out: std::env::var("SUMMARY_LAYER_JSON_OUT").ok().map(PathBuf::from),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Aggregate {
+    calls: u64,
+    own_ns: u64,
+    inclusive_ns: u64,
+    cpu_ns: u64,
+}
+
+/// Sent from [`Layer::on_exit`] to the aggregation thread, or from [`Guard::drop`] to request the
+/// final table.
+enum Message {
+    Record {
+        span_name: &'static str,
+        own_ns: u64,
+        inclusive_ns: u64,
+        cpu_ns: u64,
+    },
+    /// The aggregation thread replies on `ack` once the table (and any configured CSV/JSON
+    /// sidecars) have been written, so `Guard::drop` doesn't return before output is flushed.
+    Finish { ack: mpsc::Sender<()> },
+}
+
+fn format_table(aggregates: &HashMap<&'static str, Aggregate>) -> String {
+    let mut rows: Vec<_> = aggregates.iter().collect();
+    rows.sort_by(|(_, a), (_, b)| b.inclusive_ns.cmp(&a.inclusive_ns));
+
+    let mut out = String::from("span_name,calls,own_time,inclusive_time,cpu_time\n");
+    for (span_name, aggregate) in rows {
+        let own = std::time::Duration::from_nanos(aggregate.own_ns);
+        let inclusive = std::time::Duration::from_nanos(aggregate.inclusive_ns);
+        let cpu = std::time::Duration::from_nanos(aggregate.cpu_ns);
+        out.push_str(&format!(
+            "{span_name},{},{own:.2?},{inclusive:.2?},{cpu:.2?}\n",
+            aggregate.calls
+        ));
+    }
+    out
+}
+
+fn write_csv(path: &std::path::Path, table: &str) {
+    if let Err(e) = std::fs::write(path, table) {
+        err_msg!("failed to write summary CSV to {}: {e}", path.display());
+    }
+}
+
+fn write_json(path: &std::path::Path, aggregates: &HashMap<&'static str, Aggregate>) {
+    let mut rows: Vec<_> = aggregates.iter().collect();
+    rows.sort_by(|(_, a), (_, b)| b.inclusive_ns.cmp(&a.inclusive_ns));
+
+    let entries: Vec<String> = rows
+        .into_iter()
+        .map(|(span_name, aggregate)| {
+            format!(
+                "{{\"span_name\":\"{span_name}\",\"calls\":{},\"own_ns\":{},\"inclusive_ns\":{},\"cpu_ns\":{}}}",
+                aggregate.calls, aggregate.own_ns, aggregate.inclusive_ns, aggregate.cpu_ns
+            )
+        })
+        .collect();
+    let json = format!("[{}]", entries.join(","));
+
+    if let Err(e) = std::fs::write(path, json) {
+        err_msg!("failed to write summary JSON to {}: {e}", path.display());
+    }
+}
+
+pub struct Layer {
+    tx: mpsc::Sender<Message>,
+    init_time: Instant,
+}
+
+impl Layer {
+    pub fn new(config: Config) -> (Self, Guard) {
+        let (tx, rx) = mpsc::channel::<Message>();
+
+        std::thread::spawn(move || {
+            let mut aggregates: HashMap<&'static str, Aggregate> = HashMap::new();
+
+            while let Ok(msg) = rx.recv() {
+                match msg {
+                    Message::Record {
+                        span_name,
+                        own_ns,
+                        inclusive_ns,
+                        cpu_ns,
+                    } => {
+                        let aggregate = aggregates.entry(span_name).or_default();
+                        aggregate.calls += 1;
+                        aggregate.own_ns += own_ns;
+                        aggregate.inclusive_ns += inclusive_ns;
+                        aggregate.cpu_ns += cpu_ns;
+                    }
+                    Message::Finish { ack } => {
+                        let table = format_table(&aggregates);
+                        print!("{table}");
+
+                        if let Some(path) = &config.csv_out {
+                            write_csv(path, &table);
+                        }
+                        if let Some(path) = &config.json_out {
+                            write_json(path, &aggregates);
+                        }
+
+                        let _ = ack.send(());
+                    }
+                }
+            }
+        });
+
+        let guard = Guard { tx: tx.clone() };
+        let layer = Self {
+            tx,
+            init_time: Instant::now(),
+        };
+
+        (layer, guard)
+    }
+}
+
+/// Signals the aggregation thread to print (and optionally write out) the final summary table
+/// once dropped. Must be kept alive for the duration of the program, mirroring every other
+/// guard in this crate.
+pub struct Guard {
+    tx: mpsc::Sender<Message>,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let (ack_tx, ack_rx) = mpsc::channel();
+        if self.tx.send(Message::Finish { ack: ack_tx }).is_ok() {
+            let _ = ack_rx.recv();
+        }
+    }
+}
+
+impl<S> tracing_subscriber::Layer<S> for Layer
+where
+    S: tracing::Subscriber,
+    S: for<'lookup> tracing_subscriber::registry::LookupSpan<'lookup>,
+{
+    fn on_new_span(
+        &self,
+        _attrs: &span::Attributes<'_>,
+        id: &span::Id,
+        ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        let Some(span) = ctx.span(id) else {
+            err_msg!("failed to get span on_new_span");
+            return;
+        };
+
+        span.extensions_mut().insert(SummaryMetadata::default());
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: tracing_subscriber::layer::Context<'_, S>) {
+        with_span_storage_mut::<SummaryMetadata, _>(id, ctx, |storage| {
+            storage
+                .start_time
+                .replace(self.init_time.elapsed().as_nanos() as u64);
+            storage.cpu_start_time.replace(
+                clock_gettime(ClockId::CLOCK_THREAD_CPUTIME_ID).expect("failed to get system time"),
+            );
+        });
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: tracing_subscriber::layer::Context<'_, S>) {
+        let inclusive_ns = if let Some(span) = ctx.span(id) {
+            if let Some(storage) = span.extensions_mut().get_mut::<SummaryMetadata>() {
+                let end_cpu_time = clock_gettime(ClockId::CLOCK_THREAD_CPUTIME_ID)
+                    .expect("failed to get system time");
+                let end_time = self.init_time.elapsed().as_nanos() as u64;
+                let start_time = storage.start_time.unwrap_or(end_time);
+                let inclusive_ns = end_time - start_time;
+
+                let cpu_diff = (end_cpu_time - storage.cpu_start_time.unwrap_or(end_cpu_time))
+                    .num_nanoseconds();
+                let cpu_ns = if cpu_diff > 0 { cpu_diff as u64 } else { 0 };
+
+                let own_ns = inclusive_ns.saturating_sub(storage.child_inclusive_ns);
+
+                let _ = self.tx.send(Message::Record {
+                    span_name: span.name(),
+                    own_ns,
+                    inclusive_ns,
+                    cpu_ns,
+                });
+
+                inclusive_ns
+            } else {
+                err_msg!("failed to get storage on_exit");
+                0
+            }
+        } else {
+            err_msg!("failed to get span on_exit");
+            0
+        };
+
+        if let Some(parent_id) = ctx.span(id).and_then(|x| x.parent().map(|y| y.id())) {
+            with_span_storage_mut(&parent_id, ctx, |storage: &mut SummaryMetadata| {
+                storage.child_inclusive_ns += inclusive_ns;
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use rusty_fork::rusty_fork_test;
+    use tracing::debug_span;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::prelude::*;
+
+    use super::*;
+
+    // Since tracing_subscriber::registry() is a global singleton, we need to run the tests in separate processes.
+    rusty_fork_test! {
+        #[test]
+        fn summary1() {
+            let (layer, _guard) = Layer::new(Config::default());
+            tracing_subscriber::registry().with(layer).init();
+
+            let _scope = debug_span!("parent span").entered();
+            thread::sleep(Duration::from_millis(20));
+
+            {
+                let _scope2 = debug_span!("child span").entered();
+                thread::sleep(Duration::from_millis(20));
+            }
+        }
+    }
+}
@@ -12,6 +12,9 @@
 //!     `PerfettoLayer`: uses a local or system-wide perfetto tracing service to record data.
 //!     `IttApiLayer`: logs data in Intel's [ITT API](https://www.intel.com/content/www/us/en/docs/vtune-profiler/user-guide/2023-1/instrumentation-and-tracing-technology-apis.html)
 //!     `TracyLayer`: re-exports the `tracing_tracy::TracyLayer`.
+//!     `OtelLayer`: exports spans to an OTLP collector (Jaeger, Tempo, etc.).
+//!     `SummaryLayer`: accumulates per-span-name call counts and durations off the hot path,
+//!         printing a sorted summary table once its guard drops.
 //!
 //! `init_tracing` is a convenience function that initializes the tracing with the default values
 //! depending on the features enabled and environment variables set.
@@ -33,7 +36,7 @@
 //! fn main() {
 //!     // Initialize the tracing with the default values
 //!     // Note that the guard must be kept alive for the duration of the program.
-//!     let _guard = init_tracing().unwrap();
+//!     let (_guard, _handle) = init_tracing().unwrap();
 //!     
 //!     entry_point();
 //! }
@@ -45,11 +48,20 @@
 //! # Features
 //! The `panic` feature will turn eprintln! into panic!, causing the program to halt on errors.
 
+#[cfg(feature = "alloc_counters")]
+mod alloc;
 mod data;
 mod env_utils;
 mod errors;
+mod filename_builder;
+mod filename_utils;
 mod layers;
+#[cfg(feature = "metatrace")]
+mod metatrace;
+mod trace_retention;
 
+#[cfg(feature = "alloc_counters")]
+pub use alloc::CountingAllocator;
 #[cfg(feature = "ittapi")]
 pub use layers::ittapi::Layer as IttApiLayer;
 #[cfg(feature = "perf_counters")]
@@ -57,6 +69,7 @@ pub use layers::print_perf_counters::Layer as PrintPerfCountersLayer;
 pub use layers::{
     csv::Layer as CsvLayer,
     graph::{Config as PrintTreeConfig, Layer as PrintTreeLayer},
+    summary::{Config as SummaryConfig, Layer as SummaryLayer},
 };
 #[cfg(feature = "perf_counters")]
 pub use {
@@ -65,15 +78,31 @@ pub use {
     perf_event::events::Software as PerfSoftwareEvent,
 };
 
+#[cfg(feature = "opentelemetry")]
+pub use layers::otel::{Layer as OtelLayer, OtelError, OtelGuard};
+
 #[cfg(feature = "perfetto")]
 pub use layers::perfetto::{Layer as PerfettoLayer, PerfettoSettings as PerfettoCategorySettings};
 #[cfg(feature = "perfetto")]
-pub use perfetto_sys::PerfettoGuard;
+pub use perfetto_sys::{IntegrityMode, PerfettoGuard};
+#[cfg(feature = "perfetto")]
+pub use perfetto_sys::{
+    strip_integrity_header, verify_trace_file, verify_trace_sidecar, write_integrity_sidecar,
+};
+#[cfg(feature = "perfetto_proto")]
+pub use data::PerfettoTraceWriter;
 
 #[cfg(feature = "tracy")]
 pub use tracing_tracy::TracyLayer;
 
-pub use layers::init_tracing::init_tracing;
+pub use layers::init_tracing::{init_tracing, Backend, ReloadHandle};
+#[cfg(feature = "gen_filename")]
+pub use layers::init_tracing::init_tracing_with_builder;
+
+#[cfg(feature = "gen_filename")]
+pub use filename_builder::TraceFilenameBuilder;
+
+pub use trace_retention::{RetentionError, RetentionPolicy, TraceRetention};
 
 #[cfg(test)]
 mod tests {
@@ -139,7 +168,7 @@ mod tests {
     rusty_fork_test! {
         #[test]
         fn all_layers() {
-            let _guard = init_tracing().unwrap();
+            let (_guard, _handle) = init_tracing().unwrap();
 
             _ = make_spans();
         }
@@ -0,0 +1,126 @@
+// Copyright 2024-2025 Irreducible Inc.
+
+//! Opt-in self-profiling metatrace, analogous to Perfetto's own internal metatrace: measures how
+//! much observer effect this crate's layers introduce.
+//!
+//! Gated behind the `metatrace` feature and, at runtime, the `TRACING_PROFILE_METATRACE`
+//! environment variable (off by default). When enabled, [`Timer`] samples wrap the hot paths of
+//! `EventCounts::record`, the Perfetto FFI `set_counter_*` calls, and each layer's `on_event`,
+//! accumulating call count and total elapsed time per call site. [`report`] renders the result as
+//! a [`LogTree`], which `init_tracing` prints when its guard drops.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use crate::{data::LogTree, env_utils::get_bool_env_var};
+
+#[derive(Default)]
+struct Site {
+    calls: u64,
+    total: Duration,
+}
+
+#[derive(Default)]
+struct Store {
+    sites: HashMap<&'static str, Site>,
+}
+
+fn store() -> &'static Mutex<Store> {
+    static STORE: OnceLock<Mutex<Store>> = OnceLock::new();
+    STORE.get_or_init(|| Mutex::new(Store::default()))
+}
+
+/// Whether metatrace recording is enabled, cached on first call. Controlled by the
+/// `TRACING_PROFILE_METATRACE` environment variable.
+pub fn is_enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| get_bool_env_var("TRACING_PROFILE_METATRACE", false))
+}
+
+/// Record one sample of `elapsed` time spent at `site`. A no-op unless [`is_enabled`].
+pub fn record(site: &'static str, elapsed: Duration) {
+    if !is_enabled() {
+        return;
+    }
+
+    let Ok(mut store) = store().lock() else {
+        return;
+    };
+    let entry = store.sites.entry(site).or_default();
+    entry.calls += 1;
+    entry.total += elapsed;
+}
+
+/// RAII sample: records the elapsed time at `site` when dropped.
+///
+/// [`Timer::start`] returns `None` when metatrace is disabled, so disabled call sites pay no
+/// `Instant::now()` cost beyond the `is_enabled` check.
+pub struct Timer {
+    site: &'static str,
+    start: Instant,
+}
+
+impl Timer {
+    pub fn start(site: &'static str) -> Option<Self> {
+        is_enabled().then(|| Self {
+            site,
+            start: Instant::now(),
+        })
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        record(self.site, self.start.elapsed());
+    }
+}
+
+/// Renders the accumulated call counts and total durations as a [`LogTree`], one event per call
+/// site, sorted by name. Returns `None` if nothing has been recorded.
+pub fn report() -> Option<LogTree> {
+    let store = store().lock().ok()?;
+    if store.sites.is_empty() {
+        return None;
+    }
+
+    let mut sites: Vec<_> = store.sites.iter().collect();
+    sites.sort_by_key(|(name, _)| **name);
+
+    Some(LogTree {
+        label: "metatrace".to_string(),
+        events: sites
+            .iter()
+            .map(|(name, site)| {
+                format!(
+                    "{name}: {} calls, {} ns total",
+                    site.calls,
+                    site.total.as_nanos()
+                )
+            })
+            .collect(),
+        children: Vec::new(),
+    })
+}
+
+/// Clears all accumulated samples. Mainly useful for tests.
+#[allow(unused)]
+pub fn clear() {
+    if let Ok(mut store) = store().lock() {
+        store.sites.clear();
+    }
+}
+
+/// Prints the metatrace [`report`] (if enabled and non-empty) when dropped. Bundled into the
+/// overall `init_tracing` guard so the observer-effect summary appears once tracing shuts down.
+pub struct ReportGuard;
+
+impl Drop for ReportGuard {
+    fn drop(&mut self) {
+        if let Some(tree) = report() {
+            println!("{tree}");
+        }
+    }
+}
@@ -0,0 +1,328 @@
+// Copyright 2024-2025 Irreducible Inc.
+
+//! Retention/pruning for old `*.perfetto-trace` files, so a long-running benchmark loop that
+//! calls `TraceFilenameBuilder::build()` repeatedly can bound its disk usage automatically.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use ignore::{WalkBuilder, WalkState};
+use thiserror::Error;
+
+/// Errors that can occur while scanning or pruning trace files.
+#[derive(Debug, Error)]
+pub enum RetentionError {
+    #[error("I/O error: {0}")]
+    IoError(String),
+}
+
+/// A policy for deciding which `*.perfetto-trace` files a [`TraceRetention`] scan should keep.
+#[derive(Debug, Clone)]
+pub enum RetentionPolicy {
+    /// Keep only the `n` most recently modified trace files.
+    KeepMostRecent(usize),
+    /// Keep every trace file modified within `max_age` of now.
+    KeepNewerThan(Duration),
+    /// Keep the most recently modified trace files until their combined size would exceed
+    /// `max_bytes`.
+    MaxTotalBytes(u64),
+}
+
+/// A trace file found during a scan, along with the metadata the policy needs to rank it.
+struct TraceFile {
+    path: PathBuf,
+    modified: SystemTime,
+    size: u64,
+}
+
+/// Scans a directory for `*.perfetto-trace` files and prunes them according to a
+/// [`RetentionPolicy`].
+///
+/// The scan uses the `ignore` crate's parallel `WalkBuilder`, so it respects `.gitignore` (inside
+/// a git repository) and a `.perfetto-ignore` file, for trace-specific exclusions: a trace file
+/// matched by either is neither scanned nor removed, while any other trace file, checked in or
+/// not, is subject to the policy. Construct with [`new`](Self::new), tune with
+/// [`threads`](Self::threads)/[`max_depth`](Self::max_depth)/[`dry_run`](Self::dry_run), then
+/// call [`prune`](Self::prune).
+#[derive(Debug, Clone)]
+pub struct TraceRetention {
+    dir: PathBuf,
+    policy: RetentionPolicy,
+    threads: usize,
+    max_depth: Option<usize>,
+    dry_run: bool,
+}
+
+impl TraceRetention {
+    /// Create a retention scan over `dir` with the given policy. Defaults to a single-threaded
+    /// walk with no depth limit, and actually removes files (set [`dry_run`](Self::dry_run) to
+    /// preview instead).
+    pub fn new(dir: impl Into<PathBuf>, policy: RetentionPolicy) -> Self {
+        Self {
+            dir: dir.into(),
+            policy,
+            threads: 1,
+            max_depth: None,
+            dry_run: false,
+        }
+    }
+
+    /// Number of threads the directory walk uses.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Maximum directory depth to descend into.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Report what would be removed without actually deleting anything.
+    pub fn dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    /// Scans the configured directory and removes (or, in [`dry_run`](Self::dry_run) mode,
+    /// merely reports) the trace files that fall outside the configured policy. Returns the
+    /// files that were (or would be) removed, most recently modified first.
+    pub fn prune(&self) -> Result<Vec<PathBuf>, RetentionError> {
+        let mut traces = self.scan()?;
+        traces.sort_by(|a, b| b.modified.cmp(&a.modified));
+
+        let to_remove = match &self.policy {
+            RetentionPolicy::KeepMostRecent(keep) => traces.split_off((*keep).min(traces.len())),
+            RetentionPolicy::KeepNewerThan(max_age) => {
+                let cutoff = SystemTime::now().checked_sub(*max_age);
+                let keep_through = cutoff
+                    .map(|cutoff| traces.partition_point(|trace| trace.modified >= cutoff))
+                    .unwrap_or(traces.len());
+                traces.split_off(keep_through)
+            }
+            RetentionPolicy::MaxTotalBytes(max_bytes) => {
+                let mut total = 0u64;
+                let keep_through = traces
+                    .iter()
+                    .position(|trace| {
+                        total += trace.size;
+                        total > *max_bytes
+                    })
+                    .unwrap_or(traces.len());
+                traces.split_off(keep_through)
+            }
+        };
+
+        if !self.dry_run {
+            for trace in &to_remove {
+                std::fs::remove_file(&trace.path).map_err(|e| {
+                    RetentionError::IoError(format!("failed to remove {:?}: {e}", trace.path))
+                })?;
+            }
+        }
+
+        Ok(to_remove.into_iter().map(|trace| trace.path).collect())
+    }
+
+    /// Walks [`dir`](Self::dir) in parallel across [`threads`](Self::threads) worker threads,
+    /// collecting every `*.perfetto-trace` file not excluded by a `.gitignore` or
+    /// `.perfetto-ignore`.
+    fn scan(&self) -> Result<Vec<TraceFile>, RetentionError> {
+        let mut builder = WalkBuilder::new(&self.dir);
+        builder.add_custom_ignore_filename(".perfetto-ignore");
+        if let Some(max_depth) = self.max_depth {
+            builder.max_depth(Some(max_depth));
+        }
+
+        let traces = Mutex::new(Vec::new());
+        let error = Mutex::new(None);
+
+        builder.threads(self.threads).build_parallel().run(|| {
+            Box::new(|entry| {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        *error.lock().unwrap() = Some(RetentionError::IoError(e.to_string()));
+                        return WalkState::Quit;
+                    }
+                };
+
+                let is_trace_file = entry.file_type().is_some_and(|ft| ft.is_file())
+                    && is_perfetto_trace(entry.path());
+                if !is_trace_file {
+                    return WalkState::Continue;
+                }
+
+                let metadata = match entry.metadata() {
+                    Ok(metadata) => metadata,
+                    Err(e) => {
+                        *error.lock().unwrap() = Some(RetentionError::IoError(e.to_string()));
+                        return WalkState::Quit;
+                    }
+                };
+                let modified = match metadata.modified() {
+                    Ok(modified) => modified,
+                    Err(e) => {
+                        *error.lock().unwrap() = Some(RetentionError::IoError(e.to_string()));
+                        return WalkState::Quit;
+                    }
+                };
+
+                traces.lock().unwrap().push(TraceFile {
+                    path: entry.into_path(),
+                    modified,
+                    size: metadata.len(),
+                });
+
+                WalkState::Continue
+            })
+        });
+
+        if let Some(error) = error.into_inner().unwrap() {
+            return Err(error);
+        }
+
+        Ok(traces.into_inner().unwrap())
+    }
+}
+
+/// `*.perfetto-trace` uses two dots (e.g. `foo.perfetto-trace`), so `Path::extension` alone
+/// (which would return `trace`) isn't enough to match it.
+fn is_perfetto_trace(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.ends_with(".perfetto-trace"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    /// A fresh, empty directory under the system temp dir, removed on drop.
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "tracing_profile_trace_retention_test_{name}_{}",
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn path(&self) -> &Path {
+            &self.0
+        }
+
+        /// Writes `size` bytes to `<dir>/<name>`, sleeping briefly first so each file gets a
+        /// distinct, strictly increasing modification time.
+        fn write_trace(&self, name: &str, size: usize) -> PathBuf {
+            thread::sleep(Duration::from_millis(10));
+            let path = self.0.join(name);
+            std::fs::write(&path, vec![0u8; size]).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn keep_most_recent_removes_oldest_traces() {
+        let dir = TestDir::new("keep_most_recent");
+        dir.write_trace("a.perfetto-trace", 1);
+        dir.write_trace("b.perfetto-trace", 1);
+        let newest = dir.write_trace("c.perfetto-trace", 1);
+
+        let removed = TraceRetention::new(dir.path(), RetentionPolicy::KeepMostRecent(1))
+            .prune()
+            .unwrap();
+
+        assert_eq!(removed.len(), 2);
+        assert!(!removed.contains(&newest));
+        assert!(newest.exists());
+        assert!(!dir.path().join("a.perfetto-trace").exists());
+        assert!(!dir.path().join("b.perfetto-trace").exists());
+    }
+
+    #[test]
+    fn keep_newer_than_removes_only_stale_traces() {
+        let dir = TestDir::new("keep_newer_than");
+        let old = dir.write_trace("old.perfetto-trace", 1);
+        thread::sleep(Duration::from_millis(50));
+        let cutoff = Duration::from_millis(25);
+        let fresh = dir.write_trace("fresh.perfetto-trace", 1);
+
+        let removed = TraceRetention::new(dir.path(), RetentionPolicy::KeepNewerThan(cutoff))
+            .prune()
+            .unwrap();
+
+        assert_eq!(removed, vec![old.clone()]);
+        assert!(!old.exists());
+        assert!(fresh.exists());
+    }
+
+    #[test]
+    fn max_total_bytes_keeps_most_recent_until_budget_exceeded() {
+        let dir = TestDir::new("max_total_bytes");
+        let a = dir.write_trace("a.perfetto-trace", 10);
+        let b = dir.write_trace("b.perfetto-trace", 10);
+        let c = dir.write_trace("c.perfetto-trace", 10);
+
+        let removed = TraceRetention::new(dir.path(), RetentionPolicy::MaxTotalBytes(15))
+            .prune()
+            .unwrap();
+
+        // `c` alone (10 bytes) fits the budget; adding `b` pushes the running total past it, so
+        // both `b` and `a` are removed, most recently modified first.
+        assert_eq!(removed, vec![b, a]);
+        assert!(c.exists());
+        assert!(!dir.path().join("b.perfetto-trace").exists());
+        assert!(!dir.path().join("a.perfetto-trace").exists());
+    }
+
+    #[test]
+    fn dry_run_reports_without_deleting() {
+        let dir = TestDir::new("dry_run");
+        let stale = dir.write_trace("stale.perfetto-trace", 1);
+
+        let removed = TraceRetention::new(dir.path(), RetentionPolicy::KeepMostRecent(0))
+            .dry_run()
+            .prune()
+            .unwrap();
+
+        assert_eq!(removed, vec![stale.clone()]);
+        assert!(stale.exists());
+    }
+
+    #[test]
+    fn non_trace_and_ignored_files_are_left_alone() {
+        let dir = TestDir::new("ignored_files");
+        dir.write_trace("keep.txt", 1);
+        std::fs::write(
+            dir.path().join(".perfetto-ignore"),
+            "excluded.perfetto-trace\n",
+        )
+        .unwrap();
+        dir.write_trace("excluded.perfetto-trace", 1);
+
+        let removed = TraceRetention::new(dir.path(), RetentionPolicy::KeepMostRecent(0))
+            .prune()
+            .unwrap();
+
+        assert!(removed.is_empty());
+        assert!(dir.path().join("keep.txt").exists());
+        assert!(dir.path().join("excluded.perfetto-trace").exists());
+    }
+}